@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+use crate::cues::Cue;
+use crate::duration_model::DurationModel;
+use crate::rehearsal::{self, Run};
+
+/// Where the autopilot gets the speaking time of each cue from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutopilotTiming {
+    /// The latest saved rehearsal of the script, with `duration-model` for cues it did not reach.
+    #[default]
+    Rehearsal,
+    /// The duration model's prediction from the text of the cue.
+    DurationModel,
+    /// The text of the cue read at `words_per_minute`.
+    WordsPerMinute,
+}
+
+/// Advancing the deadline without a speaker, as for a kiosk.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AutopilotSettings {
+    /// Start with the autopilot on.
+    pub enabled: bool,
+    pub timing: AutopilotTiming,
+    pub words_per_minute: f64,
+}
+
+impl Default for AutopilotSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timing: AutopilotTiming::Rehearsal,
+            words_per_minute: 150.0,
+        }
+    }
+}
+
+/// The autopilot while it is on, with the rehearsal it follows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Autopilot {
+    pub timing: AutopilotTiming,
+    run: Option<Run>,
+    words_per_minute: f64,
+}
+
+/// The saved rehearsal the autopilot follows for the script with the given name, if its timing
+/// comes from one.
+pub fn rehearsal_to_follow(settings: &AutopilotSettings, script: &str) -> Option<Run> {
+    if settings.timing != AutopilotTiming::Rehearsal {
+        return None;
+    }
+    let run = rehearsal::runs_of(rehearsal::load_runs(), script).pop();
+    if run.is_none() {
+        log::warn!("No rehearsal of {script} to follow, so the autopilot uses the duration model");
+    }
+    run
+}
+
+impl Autopilot {
+    /// Turns the autopilot on, following `run` where it reached.
+    pub fn new(settings: &AutopilotSettings, run: Option<Run>) -> Self {
+        Self {
+            timing: settings.timing,
+            run,
+            words_per_minute: settings.words_per_minute,
+        }
+    }
+
+    /// Seconds to spend on the cue with the given index before moving on.
+    pub fn cue_seconds(&self, idx: usize, cue: &Cue, model: &DurationModel) -> f64 {
+        if let Some(timing) = self.run.as_ref().and_then(|r| r.timing(idx)) {
+            return timing.speaking_seconds;
+        }
+        let predicted = match self.timing {
+            AutopilotTiming::WordsPerMinute => DurationModel {
+                settings: model.settings.clone(),
+                words_per_minute: self.words_per_minute,
+            }
+            .predict(cue),
+            _ => model.predict(cue),
+        };
+        // A cue without text, like a demo, takes as long as the video does.
+        match predicted > 0.0 {
+            true => predicted,
+            false => cue.duration().as_secs_f64(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::duration_model::DurationModelSettings;
+    use crate::rehearsal::CueTiming;
+
+    fn cue(text: &str, seconds: u64) -> Cue {
+        Cue {
+            text: text.to_string(),
+            end: Duration::from_secs(seconds),
+            ..Default::default()
+        }
+    }
+
+    fn model() -> DurationModel {
+        DurationModel::new(
+            DurationModelSettings {
+                chars_per_word: 5.0,
+                ..Default::default()
+            },
+            None,
+        )
+    }
+
+    fn autopilot(timing: AutopilotTiming, run: Option<Run>) -> Autopilot {
+        let settings = AutopilotSettings {
+            timing,
+            words_per_minute: 300.0,
+            ..Default::default()
+        };
+        Autopilot::new(&settings, run)
+    }
+
+    fn rehearsed(cue: usize, speaking_seconds: f64) -> Run {
+        let mut run = Run::new(0.0, "talk.vtt".to_string());
+        run.cues.push(CueTiming {
+            cue,
+            cue_id: String::new(),
+            wall_time: 0.0,
+            video_time: 0.0,
+            rate: 1.0,
+            speaking_seconds,
+            cue_seconds: 4.0,
+        });
+        run
+    }
+
+    #[test]
+    fn follows_the_rehearsal_where_it_reached() {
+        let autopilot = autopilot(AutopilotTiming::Rehearsal, Some(rehearsed(0, 7.5)));
+        let ten_words = "abcde ".repeat(10);
+        assert_eq!(autopilot.cue_seconds(0, &cue(&ten_words, 9), &model()), 7.5);
+        // Beyond the rehearsal, the duration model takes over: ten words at 150 per minute.
+        assert_eq!(autopilot.cue_seconds(1, &cue(&ten_words, 9), &model()), 4.0);
+    }
+
+    #[test]
+    fn reads_at_its_own_words_per_minute() {
+        let autopilot = autopilot(AutopilotTiming::WordsPerMinute, None);
+        let ten_words = "abcde ".repeat(10);
+        assert_eq!(autopilot.cue_seconds(0, &cue(&ten_words, 9), &model()), 2.0);
+        let model_only = Autopilot {
+            timing: AutopilotTiming::DurationModel,
+            ..autopilot
+        };
+        assert_eq!(
+            model_only.cue_seconds(0, &cue(&ten_words, 9), &model()),
+            4.0
+        );
+    }
+
+    #[test]
+    fn gives_a_cue_without_text_the_length_of_the_video() {
+        let autopilot = autopilot(AutopilotTiming::DurationModel, None);
+        assert_eq!(autopilot.cue_seconds(0, &cue("", 9), &model()), 9.0);
+    }
+}
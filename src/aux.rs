@@ -4,6 +4,13 @@ use wasm_bindgen::prelude::*;
 use web_sys::MessageEvent;
 use yew::prelude::*;
 
+use crate::cues::Section;
+use crate::estimator::Estimate;
+use crate::lifecycle::{Phase, Summary};
+use crate::manifest::KeyBindings;
+use crate::pacing::ControllerKind;
+use crate::rehearsal::{self, Run};
+
 #[wasm_bindgen]
 extern "C" {
     fn post_message(data: JsValue);
@@ -17,32 +24,93 @@ pub struct AuxApp {
     context: CueContext,
     current_video_rate: f64,
     is_playing: bool,
+    controller: ControllerKind,
+    estimate: Option<Estimate>,
+    target_rate: f64,
+    rate_problem: Option<String>,
+    sections: Vec<Section>,
+    cue_list: Vec<String>,
+    /// Name of the script, whose rehearsals are the ones to compare.
+    script: String,
+    phase: Phase,
+    summary: Option<Summary>,
+    rehearsing: bool,
+    autopilot: bool,
+    /// The recorded runs of the script, loaded while the comparison is open.
+    rehearsals: Option<Vec<Run>>,
 }
 
 pub enum AuxAppMsg {
     ReceivedMessage(MainToAuxInterop),
     WantingToSend(AuxToMainInterop),
+    /// Opens or closes the comparison of recorded rehearsals.
+    ToggleRehearsals,
+    ExportRehearsals,
+    ClearRehearsals,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct CueContext {
     pub prev: Vec<String>,
     pub current: String,
+    pub notes: String,
+    /// Index of the section that the current cue belongs to.
+    pub section: Option<usize>,
     pub current_idx: i32,
     pub next: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum MainToAuxInterop {
-    CurrentStatus { time: f64, rate: f64, playing: bool },
+    CurrentStatus {
+        time: f64,
+        rate: f64,
+        playing: bool,
+        controller: ControllerKind,
+        estimate: Option<Estimate>,
+        target_rate: f64,
+        /// Why the video is not playing at the rate the controller asked for.
+        rate_problem: Option<String>,
+        /// Whether a rehearsal is being recorded.
+        rehearsing: bool,
+        /// Whether the autopilot advances the deadline.
+        autopilot: bool,
+    },
     CueContext(CueContext),
+    KeyBindings(KeyBindings),
+    Sections(Vec<Section>),
+    /// The text of every cue in the script, to pick one to jump to, and the name its rehearsals
+    /// are filed under.
+    CueList {
+        script: String,
+        cues: Vec<String>,
+    },
+    /// The talk has moved on to another phase; the summary is there once it is finished.
+    Lifecycle {
+        phase: Phase,
+        summary: Option<Summary>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum AuxToMainInterop {
     AdvanceDeadline,
+    /// Undo the last `AdvanceDeadline`, like after an accidental click.
+    RetreatDeadline,
     SetIsPlaying(bool),
     ResetRate,
+    SetController(ControllerKind),
+    /// Move the deadline and the video to the start of the given section.
+    JumpToSection(usize),
+    JumpToCue {
+        idx: usize,
+    },
+    /// Start or stop recording a rehearsal.
+    SetRehearsal(bool),
+    /// Turn the autopilot on or off.
+    SetAutopilot(bool),
+    /// Sent by the aux window once it has loaded, so that the main window can send it the settings.
+    Hello,
 }
 
 impl Component for AuxApp {
@@ -65,6 +133,27 @@ impl Component for AuxApp {
             AuxAppMsg::WantingToSend(AuxToMainInterop::AdvanceDeadline)
         });
 
+        let toggle_recording = {
+            let rehearsing = self.rehearsing;
+            ctx.link().callback(move |ev: MouseEvent| {
+                ev.prevent_default();
+                AuxAppMsg::WantingToSend(AuxToMainInterop::SetRehearsal(!rehearsing))
+            })
+        };
+
+        let toggle_autopilot = {
+            let autopilot = self.autopilot;
+            ctx.link().callback(move |ev: MouseEvent| {
+                ev.prevent_default();
+                AuxAppMsg::WantingToSend(AuxToMainInterop::SetAutopilot(!autopilot))
+            })
+        };
+
+        let prev_deadline = ctx.link().callback(|ev: MouseEvent| {
+            ev.prevent_default();
+            AuxAppMsg::WantingToSend(AuxToMainInterop::RetreatDeadline)
+        });
+
         let do_play = ctx.link().callback(|ev: MouseEvent| {
             ev.prevent_default();
             AuxAppMsg::WantingToSend(AuxToMainInterop::SetIsPlaying(true))
@@ -82,28 +171,131 @@ impl Component for AuxApp {
             .context
             .prev
             .iter()
-            .map(|v| html!(<p>{v}</p>))
+            .map(|v| html!(<p>{crate::markup::render(v)}</p>))
             .collect::<Html>();
         let cues_next = self
             .context
             .next
             .iter()
-            .map(|v| html!(<p>{v}</p>))
+            .map(|v| html!(<p>{crate::markup::render(v)}</p>))
+            .collect::<Html>();
+
+        let set_controller = ctx.link().callback(|ev: Event| {
+            let select: web_sys::HtmlSelectElement = ev.target_unchecked_into();
+            let kind = ControllerKind::ALL[select.selected_index().max(0) as usize];
+            AuxAppMsg::WantingToSend(AuxToMainInterop::SetController(kind))
+        });
+        let controllers = ControllerKind::ALL
+            .iter()
+            .map(|k| html!(<option selected={*k == self.controller}>{k.name()}</option>))
+            .collect::<Html>();
+
+        let sections = self
+            .sections
+            .iter()
+            .enumerate()
+            .map(|(i, section)| {
+                let class = if self.context.section == Some(i) {
+                    "list-group-item list-group-item-action active"
+                } else {
+                    "list-group-item list-group-item-action"
+                };
+                let onclick = ctx.link().callback(move |ev: MouseEvent| {
+                    ev.prevent_default();
+                    AuxAppMsg::WantingToSend(AuxToMainInterop::JumpToSection(i))
+                });
+                html!(<button type="button" {class} {onclick}>{&section.title}</button>)
+            })
+            .collect::<Html>();
+
+        let cue_list = self
+            .cue_list
+            .iter()
+            .enumerate()
+            .map(|(idx, text)| {
+                let class = if self.context.current_idx == idx as i32 {
+                    "list-group-item list-group-item-action active"
+                } else {
+                    "list-group-item list-group-item-action"
+                };
+                let onclick = ctx.link().callback(move |ev: MouseEvent| {
+                    ev.prevent_default();
+                    AuxAppMsg::WantingToSend(AuxToMainInterop::JumpToCue { idx })
+                });
+                html!(
+                    <button type="button" {class} {onclick}>
+                        <span class="text-body-secondary me-2">{idx}</span>
+                        {crate::markup::render(text)}
+                    </button>
+                )
+            })
             .collect::<Html>();
 
         html!(
             <div class="container">
                 <h1>{"Presenter view"}</h1>
+                if let Some(ref summary) = self.summary {
+                    <div class="alert alert-success">
+                        <h4 class="alert-heading">{"The talk is over"}</h4>
+                        {summary.render()}
+                        <p class="mb-0">{"Press Back to return to the last cue."}</p>
+                    </div>
+                } else if self.phase == Phase::FinalCue {
+                    <div class="alert alert-secondary">{"This is the last cue."}</div>
+                } else if self.phase == Phase::NotStarted {
+                    <div class="alert alert-secondary">{"The talk has not started yet."}</div>
+                }
                 <p>{"Video time: "}{self.current_video_time}</p>
                 <p>{"Playback rate: "}{self.current_video_rate}</p>
                 <p>{"Is playing: "}{self.is_playing}</p>
+                <p>{"Speaker rate: "}{crate::estimator::label(self.estimate, self.target_rate)}</p>
+                if let Some(ref why) = self.rate_problem {
+                    <div class="alert alert-warning">{why}</div>
+                }
+                <button class="btn btn-outline-success" onclick={prev_deadline}>{"Back"}</button>
                 <button class="btn btn-success" onclick={next_deadline}>{"Next"}</button>
                 <button class="btn btn-primary" onclick={do_play}>{"Play"}</button>
                 <button class="btn btn-warning" onclick={do_pause}>{"Pause"}</button>
                 <button class="btn btn-outline-danger" onclick={do_reset}>{"Reset rate"}</button>
-                <div>{cues_prev}</div>
-                <p class="text-danger">{&self.context.current}</p>
-                <div>{cues_next}</div>
+                <button class={if self.rehearsing { "btn btn-danger" } else { "btn btn-outline-danger" }} onclick={toggle_recording}>
+                    {if self.rehearsing { "Stop recording" } else { "Record rehearsal" }}
+                </button>
+                <button class="btn btn-outline-secondary" onclick={ctx.link().callback(|_| AuxAppMsg::ToggleRehearsals)}>{"Compare rehearsals"}</button>
+                <button class={if self.autopilot { "btn btn-info" } else { "btn btn-outline-info" }} onclick={toggle_autopilot}>
+                    {if self.autopilot { "Take over" } else { "Autopilot" }}
+                </button>
+                <label class="form-label ms-2">{"Pacing: "}
+                    <select class="form-select" onchange={set_controller}>{controllers}</select>
+                </label>
+                <div class="row">
+                    <div class="col-8">
+                        <div>{cues_prev}</div>
+                        <p class="text-danger">{crate::markup::render(&self.context.current)}</p>
+                        <div>{cues_next}</div>
+                    </div>
+                    <div class="col-4">
+                        if !self.sections.is_empty() {
+                            <div class="list-group mb-3">{sections}</div>
+                        }
+                        if !self.context.notes.is_empty() {
+                            <div class="alert alert-info" style="white-space: pre-line;">{&self.context.notes}</div>
+                        }
+                    </div>
+                </div>
+                if let Some(ref runs) = self.rehearsals {
+                    <h2 class="h5 mt-3">{"Rehearsals"}</h2>
+                    <button class="btn btn-sm btn-outline-primary" onclick={ctx.link().callback(|_| AuxAppMsg::ExportRehearsals)}>{"Export JSON"}</button>
+                    <button class="btn btn-sm btn-outline-danger" onclick={ctx.link().callback(|_| AuxAppMsg::ClearRehearsals)}>{"Delete all"}</button>
+                    if runs.is_empty() {
+                        <p>{"No rehearsals of this script have been recorded yet."}</p>
+                    } else {
+                        <div style="max-height: 40vh; overflow: auto;">{rehearsal::comparison(runs, &self.cue_list)}</div>
+                    }
+                }
+                if !self.cue_list.is_empty() {
+                    <h2 class="h5 mt-3">{"Script"}</h2>
+                    <div class="list-group" style="max-height: 40vh; overflow-y: auto;">{cue_list}</div>
+                }
             </div>
         )
     }
@@ -112,41 +304,81 @@ impl Component for AuxApp {
         if first_render {
             // Send the keyboard receiver.
             // Here we'll set up the global event listener
-            let window = web_sys::window().unwrap();
-            let listener = EventListener::new(&window, "keydown", {
-                crate::common::event_handler(ctx.link().callback(AuxAppMsg::WantingToSend))
-            });
-            self.global_keydown_listener = Some(listener);
+            self.install_keydown_listener(ctx, KeyBindings::default());
 
             // Set up a message receiver.
 
             let window = gloo::utils::window();
             self.global_msg_listener = Some(EventListener::new(&window, "message", {
-                let cb = ctx
-                    .link()
-                    .callback(|value| AuxAppMsg::ReceivedMessage(value));
+                let cb = ctx.link().callback(AuxAppMsg::ReceivedMessage);
                 move |e| {
                     let e: MessageEvent = e.clone().dyn_into().unwrap_throw();
                     cb.emit(e.data().into_serde().unwrap());
                 }
-            }))
+            }));
+
+            // Ask the main window for the settings.
+            post_message(JsValue::from_serde(&AuxToMainInterop::Hello).unwrap_throw());
         }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             AuxAppMsg::ReceivedMessage(value) => match value {
                 MainToAuxInterop::CurrentStatus {
                     time,
                     rate,
                     playing,
+                    controller,
+                    estimate,
+                    target_rate,
+                    rate_problem,
+                    rehearsing,
+                    autopilot,
                 } => {
+                    self.rehearsing = rehearsing;
+                    self.autopilot = autopilot;
+                    self.rate_problem = rate_problem;
+                    self.estimate = estimate;
+                    self.target_rate = target_rate;
                     self.current_video_time = time;
                     self.current_video_rate = rate;
                     self.is_playing = playing;
+                    self.controller = controller;
                 }
                 MainToAuxInterop::CueContext(ctx) => self.context = ctx,
+                MainToAuxInterop::KeyBindings(keys) => self.install_keydown_listener(ctx, keys),
+                MainToAuxInterop::Sections(sections) => self.sections = sections,
+                MainToAuxInterop::CueList { script, cues } => {
+                    self.cue_list = cues;
+                    if self.rehearsals.is_some() && script != self.script {
+                        self.rehearsals = Some(rehearsal::runs_of(rehearsal::load_runs(), &script));
+                    }
+                    self.script = script;
+                }
+                MainToAuxInterop::Lifecycle { phase, summary } => {
+                    self.phase = phase;
+                    self.summary = summary;
+                }
             },
+            AuxAppMsg::ToggleRehearsals => {
+                self.rehearsals = match self.rehearsals {
+                    Some(_) => None,
+                    None => Some(rehearsal::runs_of(rehearsal::load_runs(), &self.script)),
+                };
+            }
+            AuxAppMsg::ExportRehearsals => {
+                if let Err(why) = rehearsal::export_runs() {
+                    log::error!("{why}");
+                }
+                return false;
+            }
+            AuxAppMsg::ClearRehearsals => {
+                if gloo::dialogs::confirm("Delete all recorded rehearsals?") {
+                    rehearsal::clear_runs();
+                    self.rehearsals = Some(vec![]);
+                }
+            }
             AuxAppMsg::WantingToSend(value) => {
                 post_message(JsValue::from_serde(&value).unwrap_throw())
             }
@@ -154,3 +386,13 @@ impl Component for AuxApp {
         true
     }
 }
+
+impl AuxApp {
+    fn install_keydown_listener(&mut self, ctx: &Context<Self>, keys: KeyBindings) {
+        let window = web_sys::window().unwrap();
+        let listener = EventListener::new(&window, "keydown", {
+            crate::common::event_handler(ctx.link().callback(AuxAppMsg::WantingToSend), keys)
+        });
+        self.global_keydown_listener = Some(listener);
+    }
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlVideoElement};
+
+/// How the video gets to the deadline cue when the speaker has moved on ahead of it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CatchUpStrategy {
+    /// Play faster, but no faster than `max_rate`.
+    #[default]
+    FastForward,
+    /// Cut straight to the start of the deadline cue.
+    Jump,
+    /// Cut to the start of the deadline cue, fading over from the last frame.
+    Crossfade,
+    /// Fast-forward when only a little behind, and cross-fade when further behind than
+    /// `jump_after`.
+    Mixed,
+}
+
+impl CatchUpStrategy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fast-forward" => Some(CatchUpStrategy::FastForward),
+            "jump" => Some(CatchUpStrategy::Jump),
+            "crossfade" => Some(CatchUpStrategy::Crossfade),
+            "mixed" => Some(CatchUpStrategy::Mixed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct CatchUpSettings {
+    /// Used unless the deadline cue asks for another.
+    pub strategy: CatchUpStrategy,
+    /// Fastest rate to fast-forward at.
+    pub max_rate: f64,
+    /// Seconds of video behind the deadline cue beyond which `mixed` cuts instead.
+    pub jump_after: f64,
+    /// How long the cross-fade takes.
+    pub crossfade_seconds: f64,
+}
+
+impl Default for CatchUpSettings {
+    fn default() -> Self {
+        Self {
+            strategy: CatchUpStrategy::FastForward,
+            max_rate: 4.0,
+            jump_after: 10.0,
+            crossfade_seconds: 0.5,
+        }
+    }
+}
+
+impl CatchUpSettings {
+    /// Picks what to do when `behind` seconds of video separate it from the deadline cue, as one
+    /// of `FastForward`, `Jump` or `Crossfade`. The cue's own strategy, if it has one, wins over
+    /// the manifest's.
+    pub fn choose(&self, cue: Option<CatchUpStrategy>, behind: f64) -> CatchUpStrategy {
+        match cue.unwrap_or(self.strategy) {
+            CatchUpStrategy::Mixed if behind <= self.jump_after => CatchUpStrategy::FastForward,
+            CatchUpStrategy::Mixed => CatchUpStrategy::Crossfade,
+            other => other,
+        }
+    }
+}
+
+/// Progress of a cross-fade over a cut.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crossfade {
+    /// The last frame before the cut covers the video.
+    Covering,
+    /// The last frame is fading away, until this wall-clock time.
+    Fading { until: f64 },
+}
+
+/// Draws the frame the video shows right now onto the canvas, returning whether that worked.
+pub fn capture_frame(canvas: &HtmlCanvasElement, video: &HtmlVideoElement) -> bool {
+    canvas.set_width(video.video_width());
+    canvas.set_height(video.video_height());
+    let Ok(Some(context)) = canvas.get_context("2d") else {
+        return false;
+    };
+    let context: CanvasRenderingContext2d = context.unchecked_into();
+    context
+        .draw_image_with_html_video_element(video, 0.0, 0.0)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(strategy: CatchUpStrategy) -> CatchUpSettings {
+        CatchUpSettings {
+            strategy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mixed_cuts_only_when_far_behind() {
+        let mixed = settings(CatchUpStrategy::Mixed);
+        assert_eq!(mixed.choose(None, 0.5), CatchUpStrategy::FastForward);
+        assert_eq!(mixed.choose(None, 10.0), CatchUpStrategy::FastForward);
+        assert_eq!(mixed.choose(None, 10.1), CatchUpStrategy::Crossfade);
+    }
+
+    #[test]
+    fn other_strategies_apply_however_far_behind() {
+        for strategy in [
+            CatchUpStrategy::FastForward,
+            CatchUpStrategy::Jump,
+            CatchUpStrategy::Crossfade,
+        ] {
+            assert_eq!(settings(strategy).choose(None, 0.5), strategy);
+            assert_eq!(settings(strategy).choose(None, 60.0), strategy);
+        }
+    }
+
+    #[test]
+    fn the_cue_overrides_the_manifest() {
+        let fast = settings(CatchUpStrategy::FastForward);
+        assert_eq!(
+            fast.choose(Some(CatchUpStrategy::Jump), 0.5),
+            CatchUpStrategy::Jump
+        );
+        assert_eq!(
+            fast.choose(Some(CatchUpStrategy::Mixed), 30.0),
+            CatchUpStrategy::Crossfade
+        );
+        let mixed = settings(CatchUpStrategy::Mixed);
+        assert_eq!(
+            mixed.choose(Some(CatchUpStrategy::FastForward), 30.0),
+            CatchUpStrategy::FastForward
+        );
+    }
+
+    #[test]
+    fn reads_the_names_used_in_directives() {
+        assert_eq!(
+            CatchUpStrategy::from_name("fast-forward"),
+            Some(CatchUpStrategy::FastForward)
+        );
+        assert_eq!(
+            CatchUpStrategy::from_name("mixed"),
+            Some(CatchUpStrategy::Mixed)
+        );
+        assert_eq!(CatchUpStrategy::from_name("teleport"), None);
+    }
+}
@@ -1,32 +1,107 @@
-use wasm_bindgen::JsCast;
+use gloo::file::{Blob, ObjectUrl};
+use gloo::net::http::{Request, Response};
+use gloo::timers::callback::Timeout;
+use gloo::utils::format::JsValueSerdeExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{js_sys, HtmlElement, UrlSearchParams};
 use yew::prelude::*;
 
 use crate::aux::AuxToMainInterop;
+use crate::manifest::KeyBindings;
 
-pub fn event_handler(send: Callback<AuxToMainInterop>) -> impl FnMut(&Event) {
+pub fn event_handler(send: Callback<AuxToMainInterop>, keys: KeyBindings) -> impl FnMut(&Event) {
     move |e| {
         let e: KeyboardEvent = (e.clone()).dyn_into().unwrap();
         let keycode = e.key_code();
         log::info!("Pressed key {keycode}");
-        if keycode == 33 {
-            // page up (prev)
-        } else if keycode == 34 {
-            // page down (next)
+        if keys.prev.contains(&keycode) {
+            // prev
+            log::info!("Sending retreat event");
+            send.emit(AuxToMainInterop::RetreatDeadline);
+        } else if keys.next.contains(&keycode) {
+            // next
             log::info!("Sending advance event");
             send.emit(AuxToMainInterop::AdvanceDeadline);
-        } else if keycode == 27 {
-            // Esc (pause)
+        } else if keys.pause.contains(&keycode) {
+            // pause
             log::info!("Sending pause event");
             send.emit(AuxToMainInterop::SetIsPlaying(false))
-        } else if keycode == 66 {
-            // B (cancel)
+        } else if keys.cancel.contains(&keycode) {
+            // cancel
             log::info!("Sending pause event and rate reset");
             send.emit(AuxToMainInterop::SetIsPlaying(false));
             send.emit(AuxToMainInterop::ResetRate)
-        } else if keycode == 91 {
-            // P (play)
+        } else if keys.play.contains(&keycode) {
+            // play
             log::info!("Sending play event");
             send.emit(AuxToMainInterop::SetIsPlaying(true))
         }
     }
 }
+
+/// Returns the value of the given query parameter in the page URL, if it is set and not empty.
+pub fn query_param(name: &str) -> Option<String> {
+    let search = gloo::utils::window().location().search().ok()?;
+    let params = UrlSearchParams::new_with_str(&search).ok()?;
+    params.get(name).filter(|v| !v.is_empty())
+}
+
+/// Offers the value for download as a JSON file with the given name.
+pub fn download_json<T: Serialize>(value: &T, file_name: &str) -> Result<(), String> {
+    let value = JsValue::from_serde(value).map_err(|e| e.to_string())?;
+    let json: String =
+        js_sys::JSON::stringify_with_replacer_and_space(&value, &JsValue::NULL, &JsValue::from(2))
+            .map_err(|_| format!("Could not turn {file_name} into JSON"))?
+            .into();
+    let url = ObjectUrl::from(Blob::new_with_options(
+        json.as_str(),
+        Some("application/json"),
+    ));
+    let link: HtmlElement = gloo::utils::document()
+        .create_element("a")
+        .map_err(|_| "Could not create a download link".to_string())?
+        .unchecked_into();
+    let _ = link.set_attribute("href", &url);
+    let _ = link.set_attribute("download", file_name);
+    link.click();
+    // The download starts asynchronously, so the URL has to live a little longer.
+    Timeout::new(10_000, move || drop(url)).forget();
+    Ok(())
+}
+
+/// Sends a GET request, failing unless the server answers with success. `what` names the
+/// resource in error messages.
+async fn fetch(url: &str, what: &str) -> Result<Response, String> {
+    let response = Request::get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Could not fetch {what} {url}: {e}"))?;
+    if !response.ok() {
+        return Err(format!(
+            "Could not fetch {what} {url}: HTTP {} {}",
+            response.status(),
+            response.status_text()
+        ));
+    }
+    Ok(response)
+}
+
+/// Downloads the raw bytes at the given URL.
+pub async fn fetch_bytes(url: &str, what: &str) -> Result<Vec<u8>, String> {
+    fetch(url, what)
+        .await?
+        .binary()
+        .await
+        .map_err(|e| format!("Could not read {what} {url}: {e}"))
+}
+
+/// Downloads and deserializes the JSON at the given URL.
+pub async fn fetch_json<T: DeserializeOwned>(url: &str, what: &str) -> Result<T, String> {
+    fetch(url, what)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Could not parse {what} {url}: {e}"))
+}
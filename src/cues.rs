@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::directives::{self, CueDirectives};
+use crate::script::ScriptError;
+
+mod ass;
+mod srt;
+mod text;
+mod vtt;
+
+/// One timed piece of the script, independent of the file format it came from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Cue {
+    pub id: String,
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+    /// Reminders for the presenter, not part of the spoken text.
+    pub notes: String,
+    /// `key:value` settings from the cue timing line, where the format has them.
+    pub settings: BTreeMap<String, String>,
+    /// Pacing rules for this cue, from its settings and `@pace` lines.
+    pub pacing: CueDirectives,
+}
+
+impl Cue {
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Reads the pacing directives out of the settings, and out of `@pace` lines in the text and
+    /// notes, which are removed.
+    fn extract_directives(&mut self) -> Result<(), String> {
+        for (key, value) in &self.settings {
+            self.pacing.set(key, value)?;
+        }
+        self.text = directives::take_directive_lines(&self.text, &mut self.pacing)?;
+        self.notes = directives::take_directive_lines(&self.notes, &mut self.pacing)?;
+        Ok(())
+    }
+}
+
+/// A named run of cues, starting at `first_cue` and lasting until the next section.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Section {
+    pub title: String,
+    pub first_cue: usize,
+}
+
+/// A parsed script: the cues in the order they should be read.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Script {
+    pub title: Option<String>,
+    pub cues: Vec<Cue>,
+    /// Sorted by `first_cue`; may be empty if the script has no sections.
+    pub sections: Vec<Section>,
+}
+
+impl Script {
+    /// Moves `[[...]]` sections out of the cue text and into the notes.
+    pub fn extract_inline_notes(&mut self) {
+        for cue in &mut self.cues {
+            let mut text = String::with_capacity(cue.text.len());
+            let mut rest = cue.text.as_str();
+            while let Some((before, after)) = rest.split_once("[[") {
+                let Some((note, after)) = after.split_once("]]") else {
+                    break;
+                };
+                text.push_str(before);
+                add_note(&mut cue.notes, note);
+                rest = after;
+            }
+            text.push_str(rest);
+            cue.text = text.trim().to_string();
+        }
+    }
+
+    /// Collects the pacing directives of every cue.
+    pub fn extract_directives(&mut self) -> Result<(), ScriptError> {
+        for (idx, cue) in self.cues.iter_mut().enumerate() {
+            cue.extract_directives()
+                .map_err(|e| format!("cue {}: {e}", idx + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the sections with the chapters of a WebVTT chapters track.
+    ///
+    /// Each chapter starts at the first cue that is still running when the chapter begins.
+    pub fn apply_chapters(&mut self, chapters: &Script) {
+        self.sections.clear();
+        for chapter in &chapters.cues {
+            let Some(first_cue) = self.cues.iter().position(|c| c.end > chapter.start) else {
+                continue;
+            };
+            if self
+                .sections
+                .last()
+                .is_some_and(|s| s.first_cue >= first_cue)
+            {
+                continue;
+            }
+            self.sections.push(Section {
+                title: crate::markup::plain_text(&chapter.text),
+                first_cue,
+            });
+        }
+    }
+
+    /// Returns the index of the section containing the given cue.
+    pub fn section_of(&self, cue: usize) -> Option<usize> {
+        self.sections.iter().rposition(|s| s.first_cue <= cue)
+    }
+
+    /// Adds notes from a separate notes file, keyed by cue identifier.
+    pub fn apply_notes(&mut self, notes: &HashMap<String, String>) {
+        for cue in &mut self.cues {
+            if let Some(note) = notes.get(&cue.id) {
+                add_note(&mut cue.notes, note);
+            }
+        }
+    }
+}
+
+pub(crate) fn add_note(notes: &mut String, note: &str) {
+    let note = note.trim();
+    if note.is_empty() {
+        return;
+    }
+    if !notes.is_empty() {
+        notes.push('\n');
+    }
+    notes.push_str(note);
+}
+
+/// A script file format that can be turned into cues.
+pub trait CueSource {
+    fn parse(&self, text: &str) -> Result<Script, ScriptError>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptFormat {
+    Vtt,
+    Srt,
+    Ass,
+    Text,
+}
+
+impl ScriptFormat {
+    pub fn source(self) -> &'static dyn CueSource {
+        match self {
+            ScriptFormat::Vtt => &vtt::WebVtt,
+            ScriptFormat::Srt => &srt::Srt,
+            ScriptFormat::Ass => &ass::Ass,
+            ScriptFormat::Text => &text::PlainText,
+        }
+    }
+
+    /// Guesses the format from a file name or URL.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let path = name.split(['?', '#']).next().unwrap_or(name);
+        let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+        match ext.as_str() {
+            "vtt" => Some(ScriptFormat::Vtt),
+            "srt" => Some(ScriptFormat::Srt),
+            "ass" | "ssa" => Some(ScriptFormat::Ass),
+            "txt" => Some(ScriptFormat::Text),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the file contents.
+    pub fn sniff(text: &str) -> Self {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let first = lines.next().unwrap_or_default();
+        if first.starts_with("WEBVTT") {
+            ScriptFormat::Vtt
+        } else if first.eq_ignore_ascii_case("[Script Info]") {
+            ScriptFormat::Ass
+        } else if first.chars().all(|c| c.is_ascii_digit())
+            && lines.next().is_some_and(|l| l.contains("-->"))
+        {
+            ScriptFormat::Srt
+        } else {
+            ScriptFormat::Text
+        }
+    }
+}
+
+/// How strictly [`parse_timestamp`] reads a timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ClockStyle {
+    /// `[h:]m:s[.fraction]`, as used by the non-WebVTT formats: `,` is accepted in place of
+    /// `.`, and any number of digits is allowed.
+    Lenient,
+    /// WebVTT's `[hh:]mm:ss.ttt`, with exactly two digits for minutes and seconds and three for
+    /// the milliseconds.
+    WebVtt,
+}
+
+/// Parses a lenient `[h:]m:s[.fraction]` clock time, as used by the non-WebVTT formats.
+pub(crate) fn parse_clock(text: &str) -> Result<Duration, String> {
+    parse_timestamp(text, ClockStyle::Lenient)
+}
+
+/// Parses a clock time in the given style.
+pub(crate) fn parse_timestamp(text: &str, style: ClockStyle) -> Result<Duration, String> {
+    let expected = match style {
+        ClockStyle::Lenient => "expected `[hh:]mm:ss[.fff]`",
+        ClockStyle::WebVtt => "expected `[hh:]mm:ss.ttt`",
+    };
+    if text.is_empty() {
+        return Err(format!("timestamp is missing, {expected}"));
+    }
+    let (clock, fraction) = match (style, text.split_once(['.', ','])) {
+        (ClockStyle::WebVtt, _) if text.contains(',') => {
+            return Err(format!(
+                "`{text}` uses `,` instead of `.` before the milliseconds"
+            ))
+        }
+        (ClockStyle::WebVtt, None) => {
+            return Err(format!("`{text}` has no milliseconds, {expected}"))
+        }
+        (_, Some((clock, fraction))) => (clock, Some(fraction)),
+        (ClockStyle::Lenient, None) => (text, None),
+    };
+    let (hours, minutes, seconds) = match clock.split(':').collect::<Vec<_>>()[..] {
+        [m, s] => ("0", m, s),
+        [h, m, s] => (h, m, s),
+        _ => return Err(format!("`{text}` is not a timestamp, {expected}")),
+    };
+
+    // WebVTT fixes the number of digits, the other formats do not.
+    let width = |digits: usize| (style == ClockStyle::WebVtt).then_some(digits);
+    let number = |part: &str, what: &str, digits: Option<usize>| -> Result<u64, String> {
+        let well_formed = !part.is_empty()
+            && part.chars().all(|c| c.is_ascii_digit())
+            && digits.is_none_or(|d| part.len() == d);
+        if !well_formed {
+            let width = match digits {
+                Some(d) => format!("{d} digits"),
+                None => "digits".to_string(),
+            };
+            return Err(format!("in `{text}`, {what} must be {width}, {expected}"));
+        }
+        part.parse()
+            .map_err(|_| format!("in `{text}`, {what} are out of range"))
+    };
+    let hours = number(hours, "hours", None)?;
+    let minutes = number(minutes, "minutes", width(2))?;
+    let seconds = number(seconds, "seconds", width(2))?;
+    if minutes > 59 || seconds > 59 {
+        return Err(format!("in `{text}`, minutes and seconds must be below 60"));
+    }
+    let nanos = match fraction {
+        None => 0,
+        Some(fraction) => {
+            number(fraction, "fractions of a second", width(3))?;
+            // Beyond nanoseconds, the digits do not matter.
+            let digits = &fraction[..fraction.len().min(9)];
+            format!("{digits:0<9}").parse().unwrap_or_default()
+        }
+    };
+    Ok(Duration::from_secs((hours * 60 + minutes) * 60 + seconds) + Duration::from_nanos(nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn parses_lenient_clocks() {
+        let lenient = parse_clock;
+        assert_eq!(lenient("1:02"), Ok(ms(62_000)));
+        assert_eq!(lenient("1:00:02.5"), Ok(ms(3_602_500)));
+        assert_eq!(lenient("00:00:01,250"), Ok(ms(1_250)));
+        assert!(lenient("1:60").is_err());
+        assert!(lenient("12").is_err());
+        assert!(lenient("a:bc").is_err());
+        assert!(lenient("").is_err());
+    }
+
+    #[test]
+    fn parses_webvtt_clocks_strictly() {
+        let strict = |text| parse_timestamp(text, ClockStyle::WebVtt);
+        assert_eq!(strict("01:02.003"), Ok(ms(62_003)));
+        assert_eq!(strict("10:01:02.003"), Ok(ms(36_062_003)));
+        let err = strict("00:01,000").unwrap_err();
+        assert!(err.contains("instead of `.`"), "{err}");
+        let err = strict("00:01").unwrap_err();
+        assert!(err.contains("no milliseconds"), "{err}");
+        let err = strict("0:01.000").unwrap_err();
+        assert!(err.contains("minutes must be 2 digits"), "{err}");
+        let err = strict("00:01.5").unwrap_err();
+        assert!(err.contains("3 digits"), "{err}");
+    }
+
+    #[test]
+    fn sniffs_formats() {
+        assert_eq!(ScriptFormat::sniff("WEBVTT\n"), ScriptFormat::Vtt);
+        assert_eq!(ScriptFormat::sniff("[Script Info]\n"), ScriptFormat::Ass);
+        assert_eq!(
+            ScriptFormat::sniff("1\n00:00:01,000 --> 00:00:02,000\n"),
+            ScriptFormat::Srt
+        );
+        assert_eq!(ScriptFormat::sniff("00:00:01 Hello\n"), ScriptFormat::Text);
+        assert_eq!(
+            ScriptFormat::from_name("/talk.SRT?v=2"),
+            Some(ScriptFormat::Srt)
+        );
+    }
+
+    fn script(cues: &[(&str, &str)]) -> Script {
+        Script {
+            cues: cues
+                .iter()
+                .map(|(id, text)| Cue {
+                    id: id.to_string(),
+                    text: text.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn moves_inline_notes_out_of_the_text() {
+        let mut script = script(&[
+            ("a", "Hello [[smile]] world [[ point at the slide ]]"),
+            ("b", "Left [[unclosed"),
+        ]);
+        script.extract_inline_notes();
+        assert_eq!(script.cues[0].text, "Hello  world");
+        assert_eq!(script.cues[0].notes, "smile\npoint at the slide");
+        assert_eq!(script.cues[1].text, "Left [[unclosed");
+        assert_eq!(script.cues[1].notes, "");
+    }
+
+    #[test]
+    fn adds_notes_from_a_file_by_cue_id() {
+        let mut script = script(&[("a", "One [[inline]]"), ("b", "Two"), ("", "Three")]);
+        script.extract_inline_notes();
+        let notes = HashMap::from([
+            ("a".to_string(), "from the file".to_string()),
+            ("c".to_string(), "no such cue".to_string()),
+            ("".to_string(), "  ".to_string()),
+        ]);
+        script.apply_notes(&notes);
+        assert_eq!(script.cues[0].notes, "inline\nfrom the file");
+        assert_eq!(script.cues[1].notes, "");
+        assert_eq!(script.cues[2].notes, "");
+    }
+
+    #[test]
+    fn attaches_webvtt_note_blocks_to_the_following_cue() {
+        let script = vtt::WebVtt
+            .parse(
+                "WEBVTT\n\n00:01.000 --> 00:02.000\nOne\n\nNOTE\nbreathe\n\n\
+                 two\n00:03.000 --> 00:04.000\nTwo\n\nNOTE at the end\n",
+            )
+            .unwrap();
+        assert_eq!(script.cues[0].notes, "");
+        assert_eq!(script.cues[1].notes, "breathe\nat the end");
+    }
+
+    #[test]
+    fn reads_directives_from_settings_and_pace_lines() {
+        let mut script = script(&[("a", "Demo\n@pace max-rate:1.5"), ("b", "Plain")]);
+        script.cues[0]
+            .settings
+            .insert("rate".to_string(), "1.0".to_string());
+        script.cues[0].notes = "@pace stop:never".to_string();
+        script.extract_directives().unwrap();
+        let pacing = &script.cues[0].pacing;
+        assert_eq!(pacing.rate, Some(1.0));
+        assert_eq!(pacing.max_rate, Some(1.5));
+        assert!(pacing.no_stop);
+        assert_eq!(script.cues[0].text, "Demo");
+        assert_eq!(script.cues[0].notes, "");
+        assert_eq!(script.cues[1].pacing, CueDirectives::default());
+    }
+
+    #[test]
+    fn reports_the_cue_with_a_bad_directive() {
+        let mut script = script(&[("a", "Fine"), ("b", "@pace rate:fast")]);
+        let error = script.extract_directives().unwrap_err();
+        assert!(format!("{error:?}").contains("cue 2"), "{error:?}");
+    }
+}
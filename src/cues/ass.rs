@@ -0,0 +1,148 @@
+use super::{parse_clock, Cue, CueSource, Script};
+use crate::script::ScriptError;
+
+/// Advanced SubStation Alpha (and its predecessor SSA).
+///
+/// Only `Dialogue` lines from the `[Events]` section are used; styling is dropped.
+pub struct Ass;
+
+impl CueSource for Ass {
+    fn parse(&self, text: &str) -> Result<Script, ScriptError> {
+        let mut title = None;
+        let mut section = String::new();
+        let mut format: Option<Vec<String>> = None;
+        let mut cues = vec![];
+
+        for (idx, line) in text.split('\n').enumerate() {
+            let line = line.trim();
+            let line_no = idx + 1;
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line.to_ascii_lowercase();
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match (section.as_str(), key) {
+                ("[script info]", "Title") => title = Some(value.to_string()),
+                ("[events]", "Format") => {
+                    format = Some(value.split(',').map(|f| f.trim().to_lowercase()).collect())
+                }
+                ("[events]", "Dialogue") => {
+                    let format = format.as_ref().ok_or_else(|| {
+                        ScriptError::at(
+                            line_no,
+                            &[line],
+                            "`Dialogue` line before the `Format` line of [Events]".to_string(),
+                        )
+                    })?;
+                    cues.push(
+                        parse_dialogue(value, format)
+                            .map_err(|reason| ScriptError::at(line_no, &[line], reason))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        if format.is_none() {
+            return Err("the file has no [Events] section with a `Format` line"
+                .to_string()
+                .into());
+        }
+        // Dialogue lines are not required to be in order.
+        cues.sort_by_key(|c| c.start);
+        Ok(Script {
+            title,
+            cues,
+            sections: vec![],
+        })
+    }
+}
+
+fn parse_dialogue(value: &str, format: &[String]) -> Result<Cue, String> {
+    // The text is the last field and may itself contain commas.
+    let fields = value.splitn(format.len(), ',').collect::<Vec<_>>();
+    if fields.len() != format.len() {
+        return Err(format!(
+            "expected {} comma-separated fields, found {}",
+            format.len(),
+            fields.len()
+        ));
+    }
+    let field = |name: &str| {
+        format
+            .iter()
+            .position(|f| f == name)
+            .map(|i| fields[i].trim())
+            .ok_or_else(|| format!("the `Format` line has no `{name}` field"))
+    };
+    let start = parse_clock(field("start")?).map_err(|e| format!("invalid start time: {e}"))?;
+    let end = parse_clock(field("end")?).map_err(|e| format!("invalid end time: {e}"))?;
+    Ok(Cue {
+        start,
+        end,
+        text: strip_overrides(field("text")?),
+        ..Default::default()
+    })
+}
+
+/// Removes `{...}` override blocks and turns the ASS escapes into plain text.
+fn strip_overrides(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("\\N", "\n")
+        .replace("\\n", "\n")
+        .replace("\\h", "\u{a0}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const HEADER: &str =
+        "[Script Info]\nTitle: My talk\n\n[Events]\nFormat: Layer, Start, End, Style, Text\n";
+
+    #[test]
+    fn parses_a_minimal_script() {
+        let text = format!(
+            "{HEADER}Dialogue: 0,0:00:03.00,0:00:04.00,Default,Later\n\
+             Dialogue: 0,0:00:01.50,0:00:02.00,Default,{{\\i1}}Hello{{\\i0}}, world\\Nagain\n"
+        );
+        let script = Ass.parse(&text).unwrap();
+        assert_eq!(script.title.as_deref(), Some("My talk"));
+        assert_eq!(script.cues[0].start, Duration::from_millis(1_500));
+        assert_eq!(script.cues[0].text, "Hello, world\nagain");
+        assert_eq!(script.cues[1].text, "Later");
+    }
+
+    #[test]
+    fn reports_bad_dialogue_lines() {
+        let err = Ass
+            .parse(&format!(
+                "{HEADER}Dialogue: 0,0:00:xx.00,0:00:02.00,Default,Hi\n"
+            ))
+            .unwrap_err();
+        assert_eq!(err.line, Some(6));
+        assert!(err.reason.starts_with("invalid start time"), "{err}");
+
+        let err = Ass
+            .parse("[Events]\nDialogue: 0,0:00:01.00,0:00:02.00,Default,Hi\n")
+            .unwrap_err();
+        assert_eq!(err.line, Some(2));
+
+        let err = Ass.parse("[Script Info]\nTitle: x\n").unwrap_err();
+        assert_eq!(err.line, None);
+    }
+}
@@ -0,0 +1,94 @@
+use super::{parse_clock, Cue, CueSource, Script};
+use crate::script::ScriptError;
+
+/// SubRip: numbered blocks with `hh:mm:ss,mmm --> hh:mm:ss,mmm` timing lines.
+pub struct Srt;
+
+impl CueSource for Srt {
+    fn parse(&self, text: &str) -> Result<Script, ScriptError> {
+        let lines = text.split('\n').collect::<Vec<_>>();
+        let mut cues = vec![];
+        let mut idx = 0;
+        while idx < lines.len() {
+            if lines[idx].trim().is_empty() {
+                idx += 1;
+                continue;
+            }
+            let start = idx;
+            while idx < lines.len() && !lines[idx].trim().is_empty() {
+                idx += 1;
+            }
+            cues.push(parse_block(&lines[start..idx], start + 1)?);
+        }
+        Ok(Script {
+            title: None,
+            cues,
+            sections: vec![],
+        })
+    }
+}
+
+fn parse_block(block: &[&str], first_line: usize) -> Result<Cue, ScriptError> {
+    // The counter line is optional in practice, so only the timing line is required.
+    let (id, timing_idx) = if block[0].contains("-->") {
+        (String::new(), 0)
+    } else if block.len() > 1 && block[1].contains("-->") {
+        (block[0].trim().to_string(), 1)
+    } else {
+        return Err(ScriptError::at(
+            first_line,
+            block,
+            "this block has no `start --> end` timing line".to_string(),
+        ));
+    };
+
+    let (start, end) = block[timing_idx].split_once("-->").unwrap_or_default();
+    // Some editors append position coordinates after the end time.
+    let end = end.split_whitespace().next().unwrap_or_default();
+    let line_no = first_line + timing_idx;
+    let start = parse_clock(start.trim())
+        .map_err(|e| ScriptError::at(line_no, block, format!("invalid start time: {e}")))?;
+    let end = parse_clock(end)
+        .map_err(|e| ScriptError::at(line_no, block, format!("invalid end time: {e}")))?;
+
+    Ok(Cue {
+        id,
+        start,
+        end,
+        text: block[timing_idx + 1..].join("\n"),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn parses_a_minimal_script() {
+        let script = Srt
+            .parse("1\n00:00:01,000 --> 00:00:02,000 X1:0\nHello\n\n2\n00:00:03,000 --> 00:00:04,500\nBye\n")
+            .unwrap();
+        assert_eq!(script.cues.len(), 2);
+        assert_eq!(script.cues[0].id, "1");
+        assert_eq!(script.cues[0].text, "Hello");
+        assert_eq!(script.cues[1].end, Duration::from_millis(4_500));
+    }
+
+    #[test]
+    fn reports_bad_timing_lines() {
+        let err = Srt
+            .parse("1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:xx,000 --> 00:00:04,000\nBye\n")
+            .unwrap_err();
+        assert_eq!(err.line, Some(6));
+        assert_eq!(
+            err.block.as_deref(),
+            Some("2\n00:00:xx,000 --> 00:00:04,000\nBye")
+        );
+
+        let err = Srt.parse("1\nHello\n").unwrap_err();
+        assert_eq!(err.line, Some(1));
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use super::{parse_clock, Cue, CueSource, Script, Section};
+use crate::script::ScriptError;
+
+/// How long the last cue lasts if no closing timestamp is given.
+const LAST_CUE_DURATION: Duration = Duration::from_secs(5);
+
+/// Plain text where each cue starts with its timestamp: `HH:MM:SS text`.
+///
+/// Each cue lasts until the next one starts. Lines without a timestamp continue the previous
+/// cue, a timestamp with no text ends the previous cue, `## <title>` starts a new section at the
+/// next cue, and other lines starting with `#` are ignored.
+pub struct PlainText;
+
+impl CueSource for PlainText {
+    fn parse(&self, text: &str) -> Result<Script, ScriptError> {
+        let mut cues: Vec<Cue> = vec![];
+        let mut sections = vec![];
+        // Whether the last cue's end was given explicitly.
+        let mut closed = false;
+
+        for (idx, line) in text.split('\n').enumerate() {
+            let line = line.trim();
+            if let Some(title) = line.strip_prefix("## ") {
+                sections.push(Section {
+                    title: title.trim().to_string(),
+                    first_cue: cues.len(),
+                });
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (stamp, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let starts_with_digit = stamp.starts_with(|c: char| c.is_ascii_digit());
+            let time = match parse_clock(stamp) {
+                Ok(time) => time,
+                Err(e) if starts_with_digit && stamp.contains(':') => {
+                    return Err(ScriptError::at(idx + 1, &[line], e));
+                }
+                Err(_) => match cues.last_mut() {
+                    Some(cue) if !closed => {
+                        cue.text.push('\n');
+                        cue.text.push_str(line);
+                        continue;
+                    }
+                    _ => {
+                        return Err(ScriptError::at(
+                            idx + 1,
+                            &[line],
+                            "expected a line starting with a `HH:MM:SS` timestamp".to_string(),
+                        ))
+                    }
+                },
+            };
+
+            if let Some(prev) = cues.last_mut() {
+                if !closed {
+                    prev.end = time;
+                }
+            }
+            let rest = rest.trim();
+            closed = rest.is_empty();
+            if !closed {
+                cues.push(Cue {
+                    start: time,
+                    end: time + LAST_CUE_DURATION,
+                    text: rest.to_string(),
+                    ..Default::default()
+                });
+            }
+        }
+
+        sections.retain(|s| s.first_cue < cues.len());
+        Ok(Script {
+            title: None,
+            cues,
+            sections,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_minimal_script() {
+        let script = PlainText
+            .parse("# comment\n## Intro\n00:00:01 Hello\nand more\n00:00:04 Bye\n00:00:06\n")
+            .unwrap();
+        assert_eq!(script.cues.len(), 2);
+        assert_eq!(script.cues[0].text, "Hello\nand more");
+        assert_eq!(script.cues[0].end, Duration::from_secs(4));
+        assert_eq!(script.cues[1].end, Duration::from_secs(6));
+        assert_eq!(script.sections[0].title, "Intro");
+    }
+
+    #[test]
+    fn gives_the_last_cue_a_default_length() {
+        let script = PlainText.parse("00:00:01 Hello\n").unwrap();
+        assert_eq!(
+            script.cues[0].end,
+            Duration::from_secs(1) + LAST_CUE_DURATION
+        );
+    }
+
+    #[test]
+    fn reports_malformed_and_missing_timestamps() {
+        let err = PlainText.parse("00:00:01 Hi\n00:61:00 Bye\n").unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert_eq!(err.block.as_deref(), Some("00:61:00 Bye"));
+
+        let err = PlainText.parse("Hello without a time\n").unwrap_err();
+        assert_eq!(err.line, Some(1));
+    }
+}
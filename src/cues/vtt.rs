@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use super::{add_note, parse_timestamp, ClockStyle, Cue, CueSource, Script, Section};
+use crate::script::ScriptError;
+
+/// WebVTT, as produced by most subtitle editors.
+///
+/// NOTE blocks become presenter notes of the cue that follows them (or of the last cue, if
+/// they come at the end), except for `NOTE SECTION <title>`, which starts a new section at the
+/// cue that follows. NOTE blocks before the first cue are comments on the file and are dropped.
+/// STYLE and REGION blocks are skipped.
+pub struct WebVtt;
+
+impl CueSource for WebVtt {
+    fn parse(&self, text: &str) -> Result<Script, ScriptError> {
+        let lines = text.split('\n').collect::<Vec<_>>();
+        let title = match lines[0].strip_prefix("WEBVTT") {
+            Some("") => None,
+            Some(rest) if rest.starts_with([' ', '\t']) => Some(rest[1..].to_string()),
+            _ => {
+                return Err(ScriptError::at(
+                    1,
+                    &lines[..1],
+                    "the file must start with a `WEBVTT` line".to_string(),
+                ))
+            }
+        };
+
+        let mut cues: Vec<Cue> = vec![];
+        let mut pending_notes = String::new();
+        let mut sections = vec![];
+        // The header runs until the first blank line.
+        let mut idx = lines
+            .iter()
+            .position(|l| l.is_empty())
+            .unwrap_or(lines.len());
+        while idx < lines.len() {
+            if lines[idx].is_empty() {
+                idx += 1;
+                continue;
+            }
+            let start = idx;
+            while idx < lines.len() && !lines[idx].is_empty() {
+                idx += 1;
+            }
+            let block = &lines[start..idx];
+            if let Some(note) = note_text(block) {
+                match note.strip_prefix("SECTION ") {
+                    Some(title) => sections.push(Section {
+                        title: title.trim().to_string(),
+                        first_cue: cues.len(),
+                    }),
+                    None if cues.is_empty() => {}
+                    None => add_note(&mut pending_notes, &note),
+                }
+            } else if let Some(mut cue) = parse_block(block, start + 1)? {
+                cue.notes = std::mem::take(&mut pending_notes);
+                cues.push(cue);
+            }
+        }
+        if let Some(last) = cues.last_mut() {
+            add_note(&mut last.notes, &pending_notes);
+        }
+
+        sections.retain(|s| s.first_cue < cues.len());
+        Ok(Script {
+            title,
+            cues,
+            sections,
+        })
+    }
+}
+
+/// Returns the text of a NOTE block, or `None` if this is some other block.
+fn note_text(block: &[&str]) -> Option<String> {
+    let rest = block[0].strip_prefix("NOTE")?;
+    if !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let mut lines = vec![rest.trim()];
+    lines.extend(&block[1..]);
+    Some(lines.join("\n"))
+}
+
+/// Parses one block of lines, the first of which is at the given 1-based line number.
+fn parse_block(block: &[&str], first_line: usize) -> Result<Option<Cue>, ScriptError> {
+    let first = block[0];
+    for keyword in ["STYLE", "REGION"] {
+        if let Some(rest) = first.strip_prefix(keyword) {
+            if rest.is_empty() || rest.starts_with([' ', '\t']) {
+                return Ok(None);
+            }
+        }
+    }
+
+    let (id, timing_idx) = if first.contains("-->") {
+        (String::new(), 0)
+    } else if block.len() > 1 && block[1].contains("-->") {
+        (first.to_string(), 1)
+    } else {
+        return Err(ScriptError::at(
+            first_line,
+            block,
+            "this block has no `start --> end` timing line".to_string(),
+        ));
+    };
+
+    let line_no = first_line + timing_idx;
+    let (start, end, settings) = parse_timing_line(block[timing_idx])
+        .map_err(|reason| ScriptError::at(line_no, block, reason))?;
+
+    Ok(Some(Cue {
+        id,
+        start,
+        end,
+        text: block[timing_idx + 1..].join("\n"),
+        settings,
+        ..Default::default()
+    }))
+}
+
+fn parse_timing_line(line: &str) -> Result<(Duration, Duration, BTreeMap<String, String>), String> {
+    let (start, rest) = line.split_once("-->").unwrap_or((line, ""));
+    let start = parse_timestamp(start.trim(), ClockStyle::WebVtt)
+        .map_err(|e| format!("invalid start time: {e}"))?;
+    let rest = rest.trim_start();
+    let (end, settings) = rest.split_once([' ', '\t']).unwrap_or((rest, ""));
+    let end =
+        parse_timestamp(end, ClockStyle::WebVtt).map_err(|e| format!("invalid end time: {e}"))?;
+    let settings = settings
+        .split_whitespace()
+        .filter_map(|s| s.split_once(':'))
+        .filter(|(k, v)| !k.is_empty() && !v.is_empty())
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Ok((start, end, settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_minimal_script() {
+        let script = WebVtt
+            .parse("WEBVTT My talk\n\nintro\n00:01.000 --> 00:02.500 rate:1.5\nHello\nworld\n")
+            .unwrap();
+        assert_eq!(script.title.as_deref(), Some("My talk"));
+        let cue = &script.cues[0];
+        assert_eq!(cue.id, "intro");
+        assert_eq!(cue.start, Duration::from_millis(1_000));
+        assert_eq!(cue.end, Duration::from_millis(2_500));
+        assert_eq!(cue.text, "Hello\nworld");
+        assert_eq!(cue.settings.get("rate").map(String::as_str), Some("1.5"));
+    }
+
+    #[test]
+    fn requires_the_header() {
+        let err = WebVtt.parse("00:01.000 --> 00:02.000\nHi\n").unwrap_err();
+        assert_eq!(err.line, Some(1));
+    }
+
+    #[test]
+    fn reports_malformed_timestamps_with_their_block() {
+        let err = WebVtt
+            .parse("WEBVTT\n\n00:01.000 --> 00:02.000\nOne\n\n00:03,000 --> 00:04.000\nTwo\n")
+            .unwrap_err();
+        assert_eq!(err.line, Some(6));
+        assert_eq!(err.block.as_deref(), Some("00:03,000 --> 00:04.000\nTwo"));
+        assert!(err.reason.starts_with("invalid start time"), "{err}");
+    }
+
+    #[test]
+    fn reports_missing_timestamps() {
+        let err = WebVtt
+            .parse("WEBVTT\n\nid\n00:01.000 -->\nHi\n")
+            .unwrap_err();
+        assert_eq!(err.line, Some(4));
+        assert!(err.reason.contains("missing"), "{err}");
+
+        let err = WebVtt.parse("WEBVTT\n\njust text\n").unwrap_err();
+        assert_eq!(err.line, Some(3));
+        assert!(err.reason.contains("no `start --> end`"), "{err}");
+    }
+
+    #[test]
+    fn turns_notes_into_presenter_notes_and_sections() {
+        let script = WebVtt
+            .parse(
+                "WEBVTT\n\nNOTE written for the 2024 meetup\n\nNOTE SECTION Intro\n\n\
+                 00:01.000 --> 00:02.000\nHi\n\nNOTE breathe\n\n00:03.000 --> 00:04.000\nBye\n\n\
+                 STYLE\n::cue { color: red }\n\nNOTE at the end\n",
+            )
+            .unwrap();
+        assert_eq!(script.cues.len(), 2);
+        assert_eq!(script.cues[0].notes, "");
+        assert_eq!(script.cues[1].notes, "breathe\nat the end");
+        assert_eq!(script.sections[0].title, "Intro");
+        assert_eq!(script.sections[0].first_cue, 0);
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+
+use crate::catch_up::CatchUpStrategy;
+use crate::idle::IdleMode;
+
+/// Starts a line of cue text or notes that holds pacing directives instead of spoken text.
+pub const DIRECTIVE_PREFIX: &str = "@pace";
+
+/// Pacing rules for one cue, which take precedence over the pacing controller.
+///
+/// They are written as `key:value` pairs: `rate:1.0`, `min-rate:0.8`, `max-rate:1.5`,
+/// `stop:never`, `advance:auto`, `idle:<mode>` and `catch-up:<strategy>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CueDirectives {
+    /// Play the cue at exactly this rate, like a live demo that must not be sped up.
+    pub rate: Option<f64>,
+    pub min_rate: Option<f64>,
+    pub max_rate: Option<f64>,
+    /// Never bring the video to a halt inside this cue.
+    pub no_stop: bool,
+    /// Move the deadline past this cue as soon as the video reaches it, without waiting for the
+    /// speaker.
+    pub auto_advance: bool,
+    /// What to show while the video waits at the end of this cue.
+    pub idle: Option<IdleMode>,
+    /// How the video gets to this cue when the speaker is already reading it.
+    pub catch_up: Option<CatchUpStrategy>,
+}
+
+impl CueDirectives {
+    /// Applies one directive, returning `Ok(false)` if the key is not a pacing directive at all.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        let parse_rate = || match value.parse::<f64>() {
+            Ok(rate) if rate.is_finite() && rate >= 0.0 => Ok(Some(rate)),
+            _ => Err(format!("`{key}` needs a rate like 1.0, not `{value}`")),
+        };
+        match (key, value) {
+            ("rate", _) => self.rate = parse_rate()?,
+            ("min-rate", _) => self.min_rate = parse_rate()?,
+            ("max-rate", _) => self.max_rate = parse_rate()?,
+            ("stop", "never") => self.no_stop = true,
+            ("advance", "auto") => self.auto_advance = true,
+            ("idle", _) => self.idle = Some(IdleMode::parse(value)?),
+            ("catch-up", _) => {
+                self.catch_up = Some(CatchUpStrategy::from_name(value).ok_or_else(|| {
+                    format!("`catch-up` must be `fast-forward`, `jump`, `crossfade` or `mixed`, not `{value}`")
+                })?)
+            }
+            ("stop", _) => return Err(format!("`stop` can only be `never`, not `{value}`")),
+            ("advance", _) => return Err(format!("`advance` can only be `auto`, not `{value}`")),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Applies the whitespace-separated directives after a [`DIRECTIVE_PREFIX`].
+    pub fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        for word in line.split_whitespace() {
+            let Some((key, value)) = word.split_once(':') else {
+                return Err(format!("`{word}` is not a `key:value` pacing directive"));
+            };
+            if !self.set(key, value)? {
+                return Err(format!("unknown pacing directive `{key}`"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the rate the controller asked for into what this cue allows.
+    pub fn constrain(&self, rate: f64, target_rate: f64) -> f64 {
+        if let Some(fixed) = self.rate {
+            return fixed;
+        }
+        let mut rate = rate;
+        if self.no_stop {
+            rate = rate.max(self.min_rate.unwrap_or(target_rate));
+        }
+        if let Some(min) = self.min_rate {
+            // A minimum only applies while moving, or the video could never wait for the speaker.
+            if rate > 0.0 || self.no_stop {
+                rate = rate.max(min);
+            }
+        }
+        if let Some(max) = self.max_rate {
+            rate = rate.min(max);
+        }
+        rate
+    }
+}
+
+/// Removes the directive lines from `text`, applying them to `directives`.
+pub fn take_directive_lines(text: &str, directives: &mut CueDirectives) -> Result<String, String> {
+    let mut kept = vec![];
+    for line in text.lines() {
+        match line.trim().strip_prefix(DIRECTIVE_PREFIX) {
+            Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => {
+                directives.parse_line(rest)?
+            }
+            _ => kept.push(line),
+        }
+    }
+    Ok(kept.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(line: &str) -> Result<CueDirectives, String> {
+        let mut directives = CueDirectives::default();
+        directives.parse_line(line).map(|()| directives)
+    }
+
+    #[test]
+    fn parses_every_directive() {
+        assert_eq!(
+            parsed("rate:1.0 min-rate:0.8 max-rate:1.5 stop:never advance:auto"),
+            Ok(CueDirectives {
+                rate: Some(1.0),
+                min_rate: Some(0.8),
+                max_rate: Some(1.5),
+                no_stop: true,
+                auto_advance: true,
+                idle: None,
+                catch_up: None,
+            })
+        );
+        assert_eq!(parsed(""), Ok(CueDirectives::default()));
+    }
+
+    #[test]
+    fn parses_the_idle_mode() {
+        assert_eq!(
+            parsed("idle:loop=0:10-0:12.5").map(|d| d.idle),
+            Ok(Some(IdleMode::Loop([10.0, 12.5])))
+        );
+        assert!(parsed("idle:nap").is_err());
+    }
+
+    #[test]
+    fn parses_the_catch_up_strategy() {
+        assert_eq!(
+            parsed("catch-up:jump").map(|d| d.catch_up),
+            Ok(Some(CatchUpStrategy::Jump))
+        );
+        assert!(parsed("catch-up:teleport").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_directives() {
+        assert!(parsed("rate").is_err());
+        assert!(parsed("rate:fast").is_err());
+        assert!(parsed("rate:-1").is_err());
+        assert!(parsed("stop:sometimes").is_err());
+        assert!(parsed("advance:manual").is_err());
+        assert!(parsed("speed:2").is_err());
+    }
+
+    #[test]
+    fn leaves_other_settings_alone() {
+        let mut directives = CueDirectives::default();
+        assert_eq!(directives.set("align", "start"), Ok(false));
+        assert_eq!(directives, CueDirectives::default());
+    }
+
+    #[test]
+    fn a_fixed_rate_overrides_the_controller() {
+        let directives = parsed("rate:1.0 min-rate:2 max-rate:0.5").unwrap();
+        assert_eq!(directives.constrain(0.0, 1.5), 1.0);
+        assert_eq!(directives.constrain(3.0, 1.5), 1.0);
+    }
+
+    #[test]
+    fn clamps_between_min_and_max_but_still_stops() {
+        let directives = parsed("min-rate:0.8 max-rate:1.5").unwrap();
+        assert_eq!(directives.constrain(0.5, 1.2), 0.8);
+        assert_eq!(directives.constrain(2.0, 1.2), 1.5);
+        assert_eq!(directives.constrain(1.0, 1.2), 1.0);
+        assert_eq!(directives.constrain(0.0, 1.2), 0.0);
+    }
+
+    #[test]
+    fn never_stops_below_the_target_or_minimum() {
+        assert_eq!(parsed("stop:never").unwrap().constrain(0.0, 1.2), 1.2);
+        let directives = parsed("stop:never min-rate:0.5").unwrap();
+        assert_eq!(directives.constrain(0.0, 1.2), 0.5);
+    }
+
+    #[test]
+    fn takes_out_directive_lines() {
+        let mut directives = CueDirectives::default();
+        let text = take_directive_lines(
+            "Watch this.\n  @pace rate:1 \n@pacemaker stays\n@pace",
+            &mut directives,
+        );
+        assert_eq!(text.as_deref(), Ok("Watch this.\n@pacemaker stays"));
+        assert_eq!(directives.rate, Some(1.0));
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cues::Cue;
+use crate::estimator::Estimate;
+
+/// Predicts how long the speaker needs for a cue from how much text it has, so that pacing has
+/// something to go on before any cue has been timed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DurationModelSettings {
+    pub enabled: bool,
+    /// Speaking rate for languages not listed in `languages`.
+    pub words_per_minute: f64,
+    /// Speaking rate by language tag, like `"de": 130`; `de-AT` falls back to `de`.
+    pub languages: BTreeMap<String, f64>,
+    /// Letters that make up an average word. Long words take longer to say, and scripts without
+    /// spaces between words still get a sensible length.
+    pub chars_per_word: f64,
+    /// How many timed cues the prediction is worth once measurements come in.
+    pub prior_weight: f64,
+}
+
+impl Default for DurationModelSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            words_per_minute: 150.0,
+            languages: BTreeMap::new(),
+            chars_per_word: 5.5,
+            prior_weight: 3.0,
+        }
+    }
+}
+
+/// [`DurationModelSettings`] for the language of one script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DurationModel {
+    pub settings: DurationModelSettings,
+    pub words_per_minute: f64,
+}
+
+impl DurationModel {
+    pub fn new(settings: DurationModelSettings, language: Option<&str>) -> Self {
+        let words_per_minute = language
+            .and_then(|lang| {
+                let primary = lang.split(['-', '_']).next().unwrap_or(lang);
+                (settings.languages.get(lang)).or(settings.languages.get(primary))
+            })
+            .copied()
+            .unwrap_or(settings.words_per_minute);
+        Self {
+            settings,
+            words_per_minute,
+        }
+    }
+
+    /// Seconds the speaker is expected to need for the cue.
+    pub fn predict(&self, cue: &Cue) -> f64 {
+        let text = crate::markup::plain_text(&cue.text);
+        let words = text.split_whitespace().count() as f64;
+        let letters = text.chars().filter(|c| !c.is_whitespace()).count() as f64;
+        let words = (words + letters / self.settings.chars_per_word.max(1.0)) / 2.0;
+        words / self.words_per_minute.max(1.0) * 60.0
+    }
+
+    /// The expected ratio of video time to speaking time for the cue, if it has any text.
+    pub fn ratio(&self, cue: &Cue) -> Option<f64> {
+        let predicted = self.predict(cue);
+        (predicted > 0.0).then(|| cue.duration().as_secs_f64() / predicted)
+    }
+
+    /// Combines the expected ratio with the measured one, trusting the measurements more as
+    /// more cues are timed.
+    pub fn combine(&self, expected: f64, measured: Option<Estimate>) -> f64 {
+        let Some(measured) = measured else {
+            return expected;
+        };
+        let weight = self.settings.prior_weight.max(0.0);
+        let samples = measured.samples as f64;
+        (weight * expected + samples * measured.rate) / (weight + samples)
+    }
+}
+
+impl Default for DurationModel {
+    fn default() -> Self {
+        Self::new(DurationModelSettings::default(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn settings() -> DurationModelSettings {
+        DurationModelSettings {
+            enabled: true,
+            chars_per_word: 5.0,
+            languages: BTreeMap::from([("de".to_string(), 120.0), ("de-CH".to_string(), 100.0)]),
+            ..Default::default()
+        }
+    }
+
+    fn cue(text: &str, seconds: u64) -> Cue {
+        Cue {
+            text: text.to_string(),
+            end: Duration::from_secs(seconds),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn picks_the_words_per_minute_for_the_language() {
+        let wpm = |language| DurationModel::new(settings(), language).words_per_minute;
+        assert_eq!(wpm(None), 150.0);
+        assert_eq!(wpm(Some("fr")), 150.0);
+        assert_eq!(wpm(Some("de")), 120.0);
+        assert_eq!(wpm(Some("de-AT")), 120.0);
+        assert_eq!(wpm(Some("de_AT")), 120.0);
+        assert_eq!(wpm(Some("de-CH")), 100.0);
+    }
+
+    #[test]
+    fn weighs_words_by_their_length() {
+        let model = DurationModel::new(settings(), None);
+        // Ten words of average length: ten words at 150 per minute.
+        let average = "abcde ".repeat(10);
+        assert_eq!(model.predict(&cue(&average, 0)), 4.0);
+        // Five long words count as many as ten average ones.
+        let long = "abcdefghijklmno ".repeat(5);
+        assert_eq!(model.predict(&cue(&long, 0)), 4.0);
+        // Text without spaces still gets a length from its letters.
+        let unspaced = "あ".repeat(50);
+        assert_eq!(model.predict(&cue(&unspaced, 0)), 2.2);
+        // The same text is slower in a slower language.
+        let german = DurationModel::new(settings(), Some("de"));
+        assert_eq!(german.predict(&cue(&average, 0)), 5.0);
+    }
+
+    #[test]
+    fn expects_video_time_over_speaking_time() {
+        let model = DurationModel::new(settings(), None);
+        assert_eq!(model.ratio(&cue(&"abcde ".repeat(10), 8)), Some(2.0));
+        assert_eq!(model.ratio(&cue("  ", 8)), None);
+    }
+
+    #[test]
+    fn blends_with_measurements_by_prior_weight() {
+        let model = DurationModel::new(settings(), None);
+        let measured = Estimate {
+            rate: 1.0,
+            confidence: 0.5,
+            samples: 1,
+        };
+        assert_eq!(model.combine(2.0, None), 2.0);
+        assert_eq!(model.combine(2.0, Some(measured)), 1.75);
+        let many = Estimate {
+            samples: 97,
+            ..measured
+        };
+        assert_eq!(model.combine(2.0, Some(many)), 1.03);
+        let untrusted = DurationModel::new(
+            DurationModelSettings {
+                prior_weight: 0.0,
+                ..settings()
+            },
+            None,
+        );
+        assert_eq!(untrusted.combine(2.0, Some(measured)), 1.0);
+    }
+}
@@ -0,0 +1,103 @@
+use bezier_rs::{Bezier, TValue};
+use serde::{Deserialize, Serialize};
+
+/// A named easing curve from (0, 0) to (1, 1), given by the two inner control points of a
+/// cubic bezier, like CSS `cubic-bezier()`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum EasingProfile {
+    #[default]
+    Linear,
+    /// Changes quickly at first and settles gently.
+    EaseOut,
+    /// The right half of a logistic curve, as in the earlier `advanced_rate` experiment.
+    Sigmoid,
+    /// Control points `[x1, y1, x2, y2]`; `x1` and `x2` must be within 0..=1.
+    Custom([f64; 4]),
+}
+
+impl EasingProfile {
+    pub fn control_points(self) -> [f64; 4] {
+        match self {
+            EasingProfile::Linear => [0.0, 0.0, 1.0, 1.0],
+            EasingProfile::EaseOut => [0.0, 0.0, 0.58, 1.0],
+            // Matches 2 * (1 / (1 + e^(-5x)) - 0.5), normalized to end at 1.
+            EasingProfile::Sigmoid => [0.25, 0.7, 0.5, 1.0],
+            EasingProfile::Custom(points) => points,
+        }
+    }
+
+    /// Returns how far along the curve is when a fraction `x` of the transition has passed.
+    pub fn ease(self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        if self == EasingProfile::Linear {
+            return x;
+        }
+        let [x1, y1, x2, y2] = self.control_points();
+        let curve = Bezier::from_cubic_coordinates(
+            0.0,
+            0.0,
+            x1.clamp(0.0, 1.0),
+            y1,
+            x2.clamp(0.0, 1.0),
+            y2,
+            1.0,
+            1.0,
+        );
+        // With both handles inside 0..=1 the curve is a function of x, so there is one root.
+        match curve
+            .find_tvalues_for_x(x)
+            .find(|t| (-1e-9..=1.0 + 1e-9).contains(t))
+        {
+            Some(t) => curve.evaluate(TValue::Parametric(t.clamp(0.0, 1.0))).y,
+            None => x,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: [EasingProfile; 4] = [
+        EasingProfile::Linear,
+        EasingProfile::EaseOut,
+        EasingProfile::Sigmoid,
+        EasingProfile::Custom([0.1, 0.9, 0.4, 0.2]),
+    ];
+
+    #[test]
+    fn starts_at_0_and_ends_at_1() {
+        for profile in PROFILES {
+            assert!(profile.ease(0.0).abs() < 1e-6, "{profile:?}");
+            assert!((profile.ease(1.0) - 1.0).abs() < 1e-6, "{profile:?}");
+            assert!(profile.ease(-1.0).abs() < 1e-6, "{profile:?}");
+            assert!((profile.ease(2.0) - 1.0).abs() < 1e-6, "{profile:?}");
+        }
+    }
+
+    #[test]
+    fn never_goes_backwards() {
+        for profile in PROFILES {
+            let mut last = 0.0;
+            for step in 0..=100 {
+                let y = profile.ease(step as f64 / 100.0);
+                assert!(y >= last - 1e-9, "{profile:?} drops at step {step}");
+                last = y;
+            }
+        }
+    }
+
+    #[test]
+    fn sigmoid_follows_the_advanced_rate_curve() {
+        let advanced_rate = |x: f64| 2.0 * (1.0 / (1.0 + (-5.0 * x).exp()) - 0.5);
+        for x in [0.25, 0.5, 0.75] {
+            let expected = advanced_rate(x) / advanced_rate(1.0);
+            let actual = EasingProfile::Sigmoid.ease(x);
+            assert!(
+                (actual - expected).abs() < 0.05,
+                "at {x}: expected {expected}, got {actual}"
+            );
+        }
+    }
+}
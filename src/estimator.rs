@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+/// How the timing samples are combined into one rate.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Averaging {
+    #[default]
+    Mean,
+    /// Exponentially weighted moving average, favouring recent samples.
+    Ewma,
+    Median,
+    /// Mean after dropping `trim_fraction` of the samples from each end.
+    TrimmedMean,
+}
+
+/// Turns the per-cue ratios of video time to speaking time into an estimate of the speaker's rate.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct EstimatorSettings {
+    pub averaging: Averaging,
+    /// Only the most recent this many samples are used; 0 uses all of them.
+    pub window: usize,
+    /// Weight of the newest sample for `ewma`, between 0 and 1.
+    pub ewma_alpha: f64,
+    /// Fraction of samples dropped from each end for `trimmed-mean`, below 0.5.
+    pub trim_fraction: f64,
+    /// Samples outside `min_ratio..=max_ratio` are ignored, like a cue that was paused for a question.
+    pub min_ratio: f64,
+    pub max_ratio: f64,
+}
+
+impl Default for EstimatorSettings {
+    fn default() -> Self {
+        Self {
+            averaging: Averaging::Mean,
+            window: 0,
+            ewma_alpha: 0.3,
+            trim_fraction: 0.2,
+            min_ratio: 0.25,
+            max_ratio: 4.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub rate: f64,
+    /// Between 0 and 1: grows with the number of samples and shrinks with their spread.
+    pub confidence: f64,
+    /// How many samples went into the estimate.
+    pub samples: usize,
+}
+
+impl EstimatorSettings {
+    pub fn is_plausible(&self, ratio: f64) -> bool {
+        ratio.is_finite() && (self.min_ratio..=self.max_ratio).contains(&ratio)
+    }
+
+    /// Estimates the rate from the timing history, oldest first, or `None` if no sample is usable.
+    pub fn estimate(&self, history: &[f64]) -> Option<Estimate> {
+        let plausible = history
+            .iter()
+            .copied()
+            .filter(|r| self.is_plausible(*r))
+            .collect::<Vec<_>>();
+        let samples = match self.window {
+            0 => &plausible[..],
+            w => &plausible[plausible.len().saturating_sub(w)..],
+        };
+        if samples.is_empty() {
+            return None;
+        }
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let rate = match self.averaging {
+            Averaging::Mean => mean,
+            Averaging::Ewma => {
+                let alpha = self.ewma_alpha.clamp(0.0, 1.0);
+                samples[1..]
+                    .iter()
+                    .fold(samples[0], |avg, r| alpha * r + (1.0 - alpha) * avg)
+            }
+            Averaging::Median | Averaging::TrimmedMean => {
+                let mut sorted = samples.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let trim = match self.averaging {
+                    Averaging::Median => (sorted.len() - 1) / 2,
+                    _ => (sorted.len() as f64 * self.trim_fraction.clamp(0.0, 0.49)) as usize,
+                };
+                let kept = &sorted[trim..sorted.len() - trim];
+                kept.iter().sum::<f64>() / kept.len() as f64
+            }
+        };
+
+        let variance = samples.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+        let spread = variance.sqrt() / mean;
+        let confidence = (n / (n + 3.0)) / (1.0 + spread);
+
+        Some(Estimate {
+            rate,
+            confidence,
+            samples: samples.len(),
+        })
+    }
+}
+
+/// Describes an estimate for display, e.g. "1.05× (70%, 6 cues)", falling back to the rate in
+/// use if there is no estimate.
+pub fn label(estimate: Option<Estimate>, target_rate: f64) -> String {
+    match estimate {
+        Some(e) => format!(
+            "{:.2}× ({:.0}%, {} cues)",
+            e.rate,
+            e.confidence * 100.0,
+            e.samples
+        ),
+        None => format!("{target_rate:.2}× (no samples)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(averaging: Averaging, history: &[f64]) -> f64 {
+        let settings = EstimatorSettings {
+            averaging,
+            ..Default::default()
+        };
+        settings.estimate(history).unwrap().rate
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn averages_in_each_mode() {
+        let history = [1.0, 2.0, 3.0, 4.0, 0.5];
+        assert!(close(rate(Averaging::Mean, &history), 2.1));
+        assert!(close(rate(Averaging::Median, &history), 2.0));
+        // 20% of five samples is one from each end.
+        assert!(close(rate(Averaging::TrimmedMean, &history), 2.0));
+        let mut ewma = 1.0;
+        for r in &history[1..] {
+            ewma = 0.3 * r + 0.7 * ewma;
+        }
+        assert!(close(rate(Averaging::Ewma, &history), ewma));
+    }
+
+    #[test]
+    fn takes_the_mean_of_the_middle_two_for_an_even_median() {
+        assert!(close(rate(Averaging::Median, &[1.0, 2.0, 3.0, 4.0]), 2.5));
+    }
+
+    #[test]
+    fn ignores_implausible_samples() {
+        let settings = EstimatorSettings::default();
+        assert!(!settings.is_plausible(f64::INFINITY));
+        assert!(!settings.is_plausible(f64::NAN));
+        assert!(!settings.is_plausible(0.1));
+        let estimate = settings.estimate(&[1.0, 10.0, 0.1, 2.0]).unwrap();
+        assert_eq!(estimate.samples, 2);
+        assert!(close(estimate.rate, 1.5));
+    }
+
+    #[test]
+    fn uses_only_the_window() {
+        let settings = EstimatorSettings {
+            window: 2,
+            ..Default::default()
+        };
+        let estimate = settings.estimate(&[3.0, 1.0, 2.0]).unwrap();
+        assert_eq!(estimate.samples, 2);
+        assert!(close(estimate.rate, 1.5));
+    }
+
+    #[test]
+    fn gives_no_estimate_without_usable_samples() {
+        let settings = EstimatorSettings::default();
+        assert_eq!(settings.estimate(&[]), None);
+        assert_eq!(settings.estimate(&[100.0]), None);
+        assert_eq!(label(None, 1.0), "1.00× (no samples)");
+    }
+
+    #[test]
+    fn grows_more_confident_with_consistent_samples() {
+        let settings = EstimatorSettings::default();
+        let few = settings.estimate(&[1.0]).unwrap().confidence;
+        let many = settings.estimate(&[1.0; 10]).unwrap().confidence;
+        let spread = settings.estimate(&[0.5, 1.5, 0.5, 1.5, 0.5, 1.5, 0.5, 1.5, 0.5, 1.5]);
+        assert!(few < many);
+        assert!(spread.unwrap().confidence < many);
+    }
+}
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sources::VideoSource;
+
+/// What the video shows while it waits at the deadline for the speaker.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleMode {
+    /// Stop on whatever frame the video reached.
+    #[default]
+    Freeze,
+    /// Show the frame at this video time, in seconds.
+    Hold(f64),
+    /// Play the video between these two times, in seconds, over and over.
+    Loop([f64; 2]),
+    /// Fade over to the image at this URL.
+    Still(String),
+}
+
+impl IdleMode {
+    /// Parses the value of an `idle:` directive: `freeze`, `hold=<time>`, `loop=<from>-<to>` or
+    /// `still=<url>`, with times in seconds or as `[hh:]mm:ss[.fff]`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (kind, arg) = value.split_once('=').unwrap_or((value, ""));
+        match kind {
+            "freeze" => Ok(IdleMode::Freeze),
+            "hold" => Ok(IdleMode::Hold(parse_time(arg)?)),
+            "loop" => {
+                let (from, to) = arg
+                    .split_once('-')
+                    .ok_or_else(|| format!("`loop` needs `from-to`, not `{arg}`"))?;
+                let (from, to) = (parse_time(from)?, parse_time(to)?);
+                if to <= from {
+                    return Err(format!("the loop `{arg}` ends before it starts"));
+                }
+                Ok(IdleMode::Loop([from, to]))
+            }
+            "still" if !arg.is_empty() => Ok(IdleMode::Still(arg.to_string())),
+            _ => Err(format!(
+                "`idle` must be `freeze`, `hold=<time>`, `loop=<from>-<to>` or `still=<url>`, not `{value}`"
+            )),
+        }
+    }
+}
+
+fn parse_time(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => Ok(seconds),
+        _ => crate::cues::parse_clock(text).map(|d| d.as_secs_f64()),
+    }
+}
+
+/// The video waiting for the speaker, and where to carry on from once they catch up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Idle {
+    pub mode: IdleMode,
+    pub resume_at: f64,
+    /// The playback rate to go back to, since a loop plays at normal speed.
+    pub resume_rate: f64,
+}
+
+impl Idle {
+    /// Stops the video where it is and starts showing `mode` instead.
+    pub fn start(mode: IdleMode, element: &dyn VideoSource) -> Self {
+        let resume_at = element.current_time();
+        let resume_rate = element.playback_rate();
+        log::info!("Waiting for the speaker at {resume_at}: {mode:?}");
+        match mode {
+            IdleMode::Freeze | IdleMode::Still(_) => element.pause(),
+            IdleMode::Hold(at) => {
+                element.pause();
+                element.set_current_time(at);
+            }
+            IdleMode::Loop([from, _]) => {
+                element.set_current_time(from);
+                if let Err(why) = element.set_rate(1.0) {
+                    log::error!("{why}");
+                }
+            }
+        }
+        Self {
+            mode,
+            resume_at,
+            resume_rate,
+        }
+    }
+
+    /// Keeps a loop going; called every time the video position is checked.
+    pub fn tick(&self, element: &dyn VideoSource) {
+        if let IdleMode::Loop([from, to]) = self.mode {
+            let now = element.current_time();
+            if now >= to || now < from {
+                element.set_current_time(from);
+            }
+        }
+    }
+
+    /// Puts the video back where it was waiting, at the rate it had.
+    pub fn leave(self, element: &dyn VideoSource) {
+        if matches!(self.mode, IdleMode::Hold(_) | IdleMode::Loop(_)) {
+            element.set_current_time(self.resume_at);
+        }
+        if let IdleMode::Loop(_) = self.mode {
+            if let Err(why) = element.set_rate(self.resume_rate) {
+                log::error!("{why}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::sources::{ReplayVideo, VideoState};
+
+    #[test]
+    fn parses_every_mode() {
+        assert_eq!(IdleMode::parse("freeze"), Ok(IdleMode::Freeze));
+        assert_eq!(IdleMode::parse("hold=0:12.5"), Ok(IdleMode::Hold(12.5)));
+        assert_eq!(IdleMode::parse("hold=7"), Ok(IdleMode::Hold(7.0)));
+        assert_eq!(
+            IdleMode::parse("loop=0:10-0:12.5"),
+            Ok(IdleMode::Loop([10.0, 12.5]))
+        );
+        assert_eq!(
+            IdleMode::parse("loop=1:00:00-3601"),
+            Ok(IdleMode::Loop([3600.0, 3601.0]))
+        );
+        assert_eq!(
+            IdleMode::parse("still=slides/thanks.png"),
+            Ok(IdleMode::Still("slides/thanks.png".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_modes() {
+        assert!(IdleMode::parse("hold").is_err());
+        assert!(IdleMode::parse("hold=soon").is_err());
+        assert!(IdleMode::parse("loop=0:10").is_err());
+        assert!(IdleMode::parse("loop=0:12.5-0:10").is_err());
+        assert!(IdleMode::parse("still").is_err());
+        assert!(IdleMode::parse("sleep").is_err());
+    }
+
+    fn video_at(time: f64) -> ReplayVideo {
+        let video = ReplayVideo::new(Rc::default());
+        video.show(Some(VideoState {
+            time,
+            rate: 0.5,
+            paused: false,
+        }));
+        video
+    }
+
+    #[test]
+    fn hold_shows_a_frame_and_resumes_where_it_stopped() {
+        let video = video_at(42.0);
+        let idle = Idle::start(IdleMode::Hold(12.5), &video);
+        assert!(video.paused());
+        assert_eq!(video.current_time(), 12.5);
+        idle.leave(&video);
+        assert_eq!(video.current_time(), 42.0);
+    }
+
+    #[test]
+    fn loop_wraps_around_and_resumes_where_it_stopped() {
+        let video = video_at(42.0);
+        let idle = Idle::start(IdleMode::Loop([10.0, 12.5]), &video);
+        assert!(!video.paused());
+        assert_eq!(video.current_time(), 10.0);
+        assert_eq!(video.playback_rate(), 1.0);
+        video.set_current_time(11.0);
+        idle.tick(&video);
+        assert_eq!(video.current_time(), 11.0);
+        video.set_current_time(12.6);
+        idle.tick(&video);
+        assert_eq!(video.current_time(), 10.0);
+        idle.leave(&video);
+        assert_eq!(video.current_time(), 42.0);
+        assert_eq!(video.playback_rate(), 0.5);
+    }
+
+    #[test]
+    fn freeze_stays_on_the_frame_it_reached() {
+        let video = video_at(42.0);
+        let idle = Idle::start(IdleMode::Freeze, &video);
+        assert!(video.paused());
+        video.set_current_time(42.1);
+        idle.leave(&video);
+        assert_eq!(video.current_time(), 42.1);
+    }
+}
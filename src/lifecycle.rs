@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+
+/// Where the talk is, from before the first cue to after the last one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// Nothing has been played yet, or the talk has returned to the title screen.
+    #[default]
+    NotStarted,
+    Running,
+    /// The speaker is reading the last cue.
+    FinalCue,
+    /// The last cue has been read.
+    Finished,
+}
+
+impl Phase {
+    /// The phase while the deadline is on the given cue of a script with `cues` cues.
+    pub fn at(deadline: usize, cues: usize) -> Phase {
+        if deadline >= cues.saturating_sub(1) {
+            Phase::FinalCue
+        } else {
+            Phase::Running
+        }
+    }
+}
+
+/// What happens before the first cue and after the last one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct LifecycleSettings {
+    /// Show the title of the talk over the video until it starts.
+    pub title_screen: bool,
+    /// Seconds after the end screen to go back to the start; 0 stays on the end screen.
+    pub return_to_title_after: f64,
+}
+
+/// How the talk went, for the end screen.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub title: Option<String>,
+    pub cues: usize,
+    /// Wall-clock time from the start of the talk to the end.
+    pub talk_seconds: f64,
+    /// Time spent speaking, without pauses.
+    pub speaking_seconds: f64,
+    /// The speaker's rate, as described by [`crate::estimator::label`].
+    pub rate: String,
+}
+
+fn minutes(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+impl Summary {
+    pub fn render(&self) -> Html {
+        html! {
+            <dl class="row mb-0">
+                <dt class="col-6">{"Cues"}</dt>
+                <dd class="col-6">{self.cues}</dd>
+                <dt class="col-6">{"Length"}</dt>
+                <dd class="col-6">{minutes(self.talk_seconds)}</dd>
+                <dt class="col-6">{"Speaking time"}</dt>
+                <dd class="col-6">{minutes(self.speaking_seconds)}</dd>
+                <dt class="col-6">{"Speaker rate"}</dt>
+                <dd class="col-6">{&self.rate}</dd>
+            </dl>
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_last_cue_is_the_final_one() {
+        assert_eq!(Phase::at(0, 3), Phase::Running);
+        assert_eq!(Phase::at(1, 3), Phase::Running);
+        assert_eq!(Phase::at(2, 3), Phase::FinalCue);
+        assert_eq!(Phase::at(0, 1), Phase::FinalCue);
+    }
+
+    #[test]
+    fn past_the_last_cue_stays_final() {
+        assert_eq!(Phase::at(3, 3), Phase::FinalCue);
+        assert_eq!(Phase::at(usize::MAX, 3), Phase::FinalCue);
+        assert_eq!(Phase::at(0, 0), Phase::FinalCue);
+    }
+
+    #[test]
+    fn starts_not_started() {
+        assert_eq!(Phase::default(), Phase::NotStarted);
+    }
+
+    #[test]
+    fn shows_minutes_and_seconds() {
+        assert_eq!(minutes(0.0), "0:00");
+        assert_eq!(minutes(59.6), "1:00");
+        assert_eq!(minutes(754.0), "12:34");
+        assert_eq!(minutes(-3.0), "0:00");
+    }
+}
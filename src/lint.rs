@@ -0,0 +1,266 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cues::Script;
+
+/// Thresholds for the checks that are a matter of taste rather than correctness.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LintSettings {
+    /// Gaps between cues longer than this many seconds are reported.
+    pub max_gap: f64,
+    /// Cues read faster than this many characters per second are reported.
+    pub max_chars_per_second: f64,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        Self {
+            max_gap: 10.0,
+            max_chars_per_second: 20.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The cue ends before it starts.
+    NegativeDuration,
+    ZeroDuration,
+    /// The cue starts before the previous cue does.
+    OutOfOrder {
+        previous: usize,
+    },
+    /// The cue starts before the previous cue has ended.
+    Overlap {
+        previous: usize,
+        seconds: f64,
+    },
+    /// There is a long stretch of video with no cue before this one.
+    Gap {
+        seconds: f64,
+    },
+    TooFast {
+        chars_per_second: f64,
+    },
+}
+
+impl Problem {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Problem::NegativeDuration | Problem::ZeroDuration | Problem::OutOfOrder { .. } => {
+                Severity::Error
+            }
+            Problem::Overlap { .. } => Severity::Warning,
+            Problem::Gap { .. } | Problem::TooFast { .. } => Severity::Info,
+        }
+    }
+}
+
+/// A problem with one cue, identified by its index in the script.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub cue: usize,
+    pub severity: Severity,
+    pub problem: Problem,
+}
+
+/// Cues are numbered from 1, as in the script file.
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cue {}: ", self.cue + 1)?;
+        match self.problem {
+            Problem::NegativeDuration => write!(f, "ends before it starts"),
+            Problem::ZeroDuration => write!(f, "has zero length"),
+            Problem::OutOfOrder { previous } => write!(f, "starts before cue {}", previous + 1),
+            Problem::Overlap { previous, seconds } => {
+                write!(f, "overlaps cue {} by {seconds:.3}s", previous + 1)
+            }
+            Problem::Gap { seconds } => write!(f, "follows a gap of {seconds:.1}s"),
+            Problem::TooFast { chars_per_second } => {
+                write!(f, "needs {chars_per_second:.1} characters per second")
+            }
+        }
+    }
+}
+
+/// Checks the script for cues that would confuse the pacing, sorted by cue index.
+pub fn lint(script: &Script, settings: &LintSettings) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut report = |cue: usize, problem: Problem| {
+        findings.push(Finding {
+            cue,
+            severity: problem.severity(),
+            problem,
+        })
+    };
+
+    for (idx, cue) in script.cues.iter().enumerate() {
+        if cue.end < cue.start {
+            report(idx, Problem::NegativeDuration);
+        } else if cue.end == cue.start {
+            report(idx, Problem::ZeroDuration);
+        } else {
+            let chars = crate::markup::plain_text(&cue.text).chars().count() as f64;
+            let chars_per_second = chars / cue.duration().as_secs_f64();
+            if chars_per_second > settings.max_chars_per_second {
+                report(idx, Problem::TooFast { chars_per_second });
+            }
+        }
+
+        let Some(previous) = idx.checked_sub(1) else {
+            continue;
+        };
+        let prev = &script.cues[previous];
+        if cue.start < prev.start {
+            report(idx, Problem::OutOfOrder { previous });
+        } else if cue.start < prev.end {
+            let seconds = (prev.end - cue.start).as_secs_f64();
+            report(idx, Problem::Overlap { previous, seconds });
+        } else {
+            let seconds = (cue.start - prev.end).as_secs_f64();
+            if seconds > settings.max_gap {
+                report(idx, Problem::Gap { seconds });
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::cues::Cue;
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue {
+            start: Duration::from_secs_f64(start),
+            end: Duration::from_secs_f64(end),
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn problems(cues: Vec<Cue>) -> Vec<(usize, Problem)> {
+        let script = Script {
+            cues,
+            ..Default::default()
+        };
+        lint(&script, &LintSettings::default())
+            .into_iter()
+            .map(|f| (f.cue, f.problem))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_clean_script() {
+        assert_eq!(
+            problems(vec![cue(0.0, 2.0, "Hi"), cue(2.0, 4.0, "Bye")]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn reports_bad_durations() {
+        assert_eq!(
+            problems(vec![cue(2.0, 1.0, "a"), cue(3.0, 3.0, "b")]),
+            vec![(0, Problem::NegativeDuration), (1, Problem::ZeroDuration)]
+        );
+    }
+
+    #[test]
+    fn reports_cues_out_of_order() {
+        assert_eq!(
+            problems(vec![cue(5.0, 6.0, "a"), cue(1.0, 2.0, "b")]),
+            vec![(1, Problem::OutOfOrder { previous: 0 })]
+        );
+    }
+
+    #[test]
+    fn reports_overlaps() {
+        assert_eq!(
+            problems(vec![cue(0.0, 2.0, "a"), cue(1.5, 3.0, "b")]),
+            vec![(
+                1,
+                Problem::Overlap {
+                    previous: 0,
+                    seconds: 0.5
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_long_gaps() {
+        assert_eq!(
+            problems(vec![cue(0.0, 1.0, "a"), cue(12.0, 13.0, "b")]),
+            vec![(1, Problem::Gap { seconds: 11.0 })]
+        );
+    }
+
+    #[test]
+    fn reports_cues_too_fast_to_read_without_counting_markup() {
+        let fast = "x".repeat(30);
+        assert_eq!(
+            problems(vec![cue(0.0, 1.0, &format!("<b>{fast}</b>"))]),
+            vec![(
+                0,
+                Problem::TooFast {
+                    chars_per_second: 30.0
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn describes_findings_with_cues_numbered_from_one() {
+        let finding = |cue, problem: Problem| {
+            Finding {
+                cue,
+                severity: problem.severity(),
+                problem,
+            }
+            .to_string()
+        };
+        assert_eq!(finding(0, Problem::ZeroDuration), "Cue 1: has zero length");
+        assert_eq!(
+            finding(2, Problem::OutOfOrder { previous: 1 }),
+            "Cue 3: starts before cue 2"
+        );
+        assert_eq!(
+            finding(
+                1,
+                Problem::Overlap {
+                    previous: 0,
+                    seconds: 0.5
+                }
+            ),
+            "Cue 2: overlaps cue 1 by 0.500s"
+        );
+    }
+
+    #[test]
+    fn rates_problems_by_severity() {
+        assert_eq!(Problem::ZeroDuration.severity(), Severity::Error);
+        assert_eq!(
+            Problem::Overlap {
+                previous: 0,
+                seconds: 1.0
+            }
+            .severity(),
+            Severity::Warning
+        );
+        assert_eq!(Problem::Gap { seconds: 20.0 }.severity(), Severity::Info);
+    }
+}
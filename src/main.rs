@@ -1,49 +1,97 @@
-use std::time::Duration;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 use aux::{AuxApp, AuxToMainInterop, MainToAuxInterop};
+use gloo::file::callbacks::FileReader;
 use gloo::utils::format::JsValueSerdeExt;
 use gloo::{events::EventListener, timers::callback::Interval};
 use wasm_bindgen::prelude::*;
-use web_sys::{js_sys::wasm_bindgen, wasm_bindgen::JsCast};
-use web_sys::{HtmlVideoElement, MessageEvent, Window};
-use webvtt::{Block, Cue};
+use web_sys::{js_sys, js_sys::wasm_bindgen, wasm_bindgen::JsCast};
+use web_sys::{HtmlCanvasElement, HtmlInputElement, HtmlVideoElement, MessageEvent, Window};
 use yew::prelude::*;
 
-use crate::aux::CueContext;
-
+use crate::autopilot::AutopilotSettings;
+use crate::catch_up::Crossfade;
+use crate::idle::{Idle, IdleMode};
+use crate::lifecycle::Phase;
+use crate::lint::{Finding, Severity};
+use crate::manifest::{Manifest, ScriptTrack};
+use crate::player::{Fetch, Host, Player};
+use crate::rate_bounds::RateBounds;
+use crate::rehearsal::Run;
+use crate::script::ScriptError;
+use crate::session::{Replay, SessionEvent, SessionLog};
+use crate::sources::{PageVideo, RateWrite, VideoSource, WallClock};
+
+mod autopilot;
 mod aux;
+mod catch_up;
 mod common;
+mod cues;
+mod directives;
+mod duration_model;
+mod easing;
+mod estimator;
+mod idle;
+mod lifecycle;
+mod lint;
+mod manifest;
+mod markup;
+mod pacer;
+mod pacing;
+mod player;
+mod rate_bounds;
+mod rehearsal;
+mod script;
+mod session;
+mod smoothing;
+mod sources;
+mod speech_clock;
 struct App {
-    subs: webvtt::File,
-    current_block: usize,
-    current_block_has_passed: bool,
-    is_playing: bool,
-    current_time: Duration,
-    current_rate: f64,
+    /// The talk itself; the component only connects it to the page.
+    player: Player,
+    script_reader: Option<FileReader>,
     high_res_callback: Option<Closure<dyn FnMut(JsValue, JsValue)>>,
     video_el: NodeRef,
-    deadline_block_idx: usize,
     global_keydown_listener: Option<EventListener>,
     global_message_listener: Option<EventListener>,
     child_window: Option<Window>,
     interval_callback: Option<Interval>,
-    block_timing_history: Vec<f64>,
-    current_block_started_at: f64,
-    target_rate: f64,
-}
-
-#[wasm_bindgen]
-extern "C" {
-    fn unixtime() -> f64;
+    visibility_listener: Option<EventListener>,
+    /// Holds the last frame before a cut, to fade from.
+    fade_canvas: NodeRef,
+    /// Every playback rate the video was given.
+    rates_written: Rc<RefCell<Vec<RateWrite>>>,
+    /// Set when the page replays a session log instead of running a talk.
+    replay: Option<Replay>,
 }
 
 enum Msg {
     Periodic,
     Playing(bool),
+    /// The video is waiting for data (`true`) or has resumed (`false`).
+    Stalled(bool),
+    /// The tab was hidden (`true`) or shown again (`false`).
+    VisibilityChanged(bool),
     RateChange,
     NextDeadline,
+    PreviousDeadline,
     NewWindow(Window),
     ReceivedFromChild(AuxToMainInterop),
+    ManifestLoaded(Result<Box<Manifest>, String>),
+    SelectScriptTrack(usize),
+    ScriptLoaded {
+        /// Index into the manifest's script tracks, or `None` for a local file.
+        track: Option<usize>,
+        name: String,
+        result: Result<Vec<u8>, ScriptError>,
+    },
+    NotesLoaded(Result<HashMap<String, String>, String>),
+    ChaptersLoaded(Result<Vec<u8>, ScriptError>),
+    ScriptFileChosen(gloo::file::File),
+    ExportSession,
+    ReplayLoaded(Result<Box<SessionLog>, String>),
 }
 
 impl Component for App {
@@ -51,30 +99,63 @@ impl Component for App {
 
     type Properties = ();
 
-    fn create(_ctx: &Context<Self>) -> Self {
-        let mut text = include_str!("../media/subs-verbose-de.vtt");
-        if text.chars().next().unwrap() == char::from_u32(0xfeff).unwrap() {
-            text = &text[3..];
+    fn create(ctx: &Context<Self>) -> Self {
+        let rates_written = Rc::new(RefCell::new(vec![]));
+        let replay = common::query_param("replay").map(|url| {
+            log::info!("Replaying the session log from {url}");
+            ctx.link().send_future(async move {
+                Msg::ReplayLoaded(session::fetch_log(&url).await.map(Box::new))
+            });
+            Replay::new(rates_written.clone())
+        });
+        // A script in the page URL replaces the manifest's. This happens before the manifest
+        // reaches `update`, so that the session log has the manifest as it was used.
+        let script = common::query_param("script");
+        let with_script = move |mut manifest: Manifest| {
+            if let Some(url) = script {
+                manifest.scripts = vec![ScriptTrack {
+                    url,
+                    label: None,
+                    language: None,
+                    format: None,
+                    notes: None,
+                    chapters: None,
+                }];
+            }
+            Box::new(manifest)
+        };
+        // A replay gets its manifest from the log.
+        match common::query_param("manifest") {
+            _ if replay.is_some() => {}
+            Some(url) => {
+                log::info!("Loading manifest from {url}");
+                ctx.link().send_future(async move {
+                    Msg::ManifestLoaded(manifest::fetch_manifest(&url).await.map(with_script))
+                });
+            }
+            None => ctx
+                .link()
+                .send_message(Msg::ManifestLoaded(Ok(with_script(Manifest::default())))),
         }
-        // log::info!("{text}");
-        let subs = webvtt::parse_file(text).unwrap();
+
+        let clock = match replay {
+            Some(ref replay) => replay.clock.clone() as _,
+            None => Rc::new(WallClock) as _,
+        };
         Self {
-            subs,
-            current_block: 0,
-            current_block_has_passed: false,
-            is_playing: false,
-            current_time: Duration::ZERO,
-            current_rate: 1.0,
+            // Only the manifest can say whether to record, so recording starts with it.
+            player: Player::new(clock, RateBounds::probe(), replay.is_none()),
+            script_reader: None,
             high_res_callback: None,
             video_el: NodeRef::default(),
-            deadline_block_idx: 0,
             global_keydown_listener: None,
             global_message_listener: None,
             child_window: None,
             interval_callback: None,
-            current_block_started_at: 0.0,
-            block_timing_history: vec![],
-            target_rate: 1.0,
+            visibility_listener: None,
+            fade_canvas: NodeRef::default(),
+            rates_written,
+            replay,
         }
     }
 
@@ -82,14 +163,26 @@ impl Component for App {
         let ontimeupdate = ctx.link().callback(|_ev| Msg::Periodic);
         let onplay = ctx.link().callback(|_ev| Msg::Playing(true));
         let onpause = ctx.link().callback(|_ev| Msg::Playing(false));
+        // Not `stalled`: that only says the download paused, and playback may go on from the buffer.
+        let onwaiting = ctx.link().callback(|_ev| Msg::Stalled(true));
+        let onplaying = ctx.link().callback(|_ev| Msg::Stalled(false));
         let onratechange = ctx.link().callback(|_ev| Msg::RateChange);
+        let retreat_deadline_block = ctx.link().callback(|ev: MouseEvent| {
+            ev.prevent_default();
+            Msg::PreviousDeadline
+        });
         let advance_deadline_block = ctx.link().callback(|ev: MouseEvent| {
             ev.prevent_default();
             Msg::NextDeadline
         });
 
+        let export_session = ctx.link().callback(|ev: MouseEvent| {
+            ev.prevent_default();
+            Msg::ExportSession
+        });
+
         let open_aux_window = {
-            let cb = ctx.link().callback(|w| Msg::NewWindow(w));
+            let cb = ctx.link().callback(Msg::NewWindow);
             Callback::from(move |ev: MouseEvent| {
                 ev.prevent_default();
                 let window = gloo::utils::window();
@@ -105,110 +198,262 @@ impl Component for App {
             })
         };
 
-        let current_block_style = if self.current_block_has_passed {
+        let on_script_file = ctx.link().batch_callback(|ev: Event| {
+            let input: HtmlInputElement = ev.target_unchecked_into();
+            input
+                .files()
+                .and_then(|files| files.get(0))
+                .map(|file| Msg::ScriptFileChosen(file.into()))
+        });
+
+        let script_tracks = if self.player.manifest.scripts.len() > 1 {
+            let onchange = ctx.link().callback(|ev: Event| {
+                let select: web_sys::HtmlSelectElement = ev.target_unchecked_into();
+                Msg::SelectScriptTrack(select.selected_index().max(0) as usize)
+            });
+            let options = self.player
+                .manifest
+                .scripts
+                .iter()
+                .enumerate()
+                .map(|(i, track)| {
+                    html!(<option selected={i == self.player.script_track}>{track.name()}</option>)
+                })
+                .collect::<Html>();
+            html! {
+                <label class="form-label">{"Script: "}
+                    <select class="form-select" {onchange}>{options}</select>
+                </label>
+            }
+        } else {
+            html!()
+        };
+
+        let script_status = match (&self.player.subs, &self.player.load_error) {
+            (Some(subs), _) => html! {
+                <p>{"Deadline block: "}{format!("{:?}", subs.cues.get(self.player.deadline_block_idx))}</p>
+            },
+            (None, Some(err)) => html! {
+                <div class="alert alert-danger">
+                    <h4 class="alert-heading">{"The script could not be loaded"}</h4>
+                    <p>
+                        if let Some(line) = err.line {
+                            <strong>{format!("Line {line}: ")}</strong>
+                        }
+                        {&err.reason}
+                    </p>
+                    if let Some(ref block) = err.block {
+                        <pre class="mb-0">{block}</pre>
+                    }
+                </div>
+            },
+            (None, None) => html! {
+                <div class="alert alert-info">{"Loading script..."}</div>
+            },
+        };
+
+        let replay_status = match self.replay.as_ref().map(|r| &r.report) {
+            None => html!(),
+            Some(None) => html! {
+                <div class="alert alert-info">{"Replaying session log..."}</div>
+            },
+            Some(Some(Ok(report))) => {
+                let class = match report.first_difference() {
+                    None => "alert alert-success",
+                    Some(_) => "alert alert-danger",
+                };
+                html!(<div {class}>{report.describe()}</div>)
+            }
+            Some(Some(Err(why))) => html! {
+                <div class="alert alert-danger">{why}</div>
+            },
+        };
+
+        let lint_findings = if self.player.lint_findings.is_empty() {
+            html!()
+        } else {
+            let items = self
+                .player
+                .lint_findings
+                .iter()
+                .map(|f| {
+                    let class = match f.severity {
+                        Severity::Error => "list-group-item list-group-item-danger",
+                        Severity::Warning => "list-group-item list-group-item-warning",
+                        Severity::Info => "list-group-item",
+                    };
+                    html!(<li {class}>{f.to_string()}</li>)
+                })
+                .collect::<Html>();
+            html! {
+                <details>
+                    <summary>{format!("Script check: {} findings", self.player.lint_findings.len())}</summary>
+                    <ul class="list-group">{items}</ul>
+                </details>
+            }
+        };
+
+        let current_block_style = if self.player.current_block_has_passed {
             "badge rounded-pill text-bg-success"
         } else {
             "badge rounded-pill text-bg-danger"
         };
+        let expected_time = self
+            .player
+            .subs
+            .as_ref()
+            .and_then(|s| s.cues.get(self.player.deadline_block_idx))
+            .filter(|_| self.player.duration_model.settings.enabled)
+            .map(|cue| self.player.duration_model.predict(cue));
+        let autopilot_time = self.player.autopilot_seconds();
+        let fade_style = match self.player.crossfade {
+            Some(Crossfade::Covering) => "opacity: 1; transition: none;".to_string(),
+            Some(Crossfade::Fading { .. }) => format!(
+                "opacity: 0; transition: opacity {}s linear;",
+                self.player.manifest.pacing.catch_up.crossfade_seconds
+            ),
+            None => "opacity: 0;".to_string(),
+        };
+        let still_shown = matches!(
+            self.player.pacer.idle,
+            Some(Idle {
+                mode: IdleMode::Still(_),
+                ..
+            })
+        );
         html! {
             <div class="">
-                <video src="/media/vid-rendered.mp4" controls={true} ref={self.video_el.clone()} muted={true}
-                {ontimeupdate} {onplay} {onpause} {onratechange}
-                style="width: 100%;"/>
+                <div style="position: relative;">
+                    <video src={self.player.manifest.video.clone()} controls={true} ref={self.video_el.clone()} muted={true}
+                    autoplay={self.player.manifest.autopilot.enabled}
+                    {ontimeupdate} {onplay} {onpause} {onratechange} {onwaiting} {onplaying}
+                    style="width: 100%;"/>
+                    <canvas ref={self.fade_canvas.clone()} class="catch-up-fade" style={fade_style} />
+                    if let Some(ref url) = self.player.pacer.still_image {
+                        <img src={url.clone()} alt="" class={classes!("idle-still", still_shown.then_some("shown"))} />
+                    }
+                    if self.player.phase == Phase::Finished {
+                        <div class="screen-overlay">
+                            <div>
+                                <h1 class="display-4">{self.player.manifest.title.clone().unwrap_or_else(|| "Thank you".to_string())}</h1>
+                                {self.player.summary().render()}
+                            </div>
+                        </div>
+                    } else if self.player.phase == Phase::NotStarted && self.player.manifest.lifecycle.title_screen {
+                        <div class="screen-overlay">
+                            <h1 class="display-4">{self.player.manifest.title.clone().unwrap_or_default()}</h1>
+                        </div>
+                    }
+                </div>
 
                 <hr />
                 <h1>
-                    <span class="badge rounded-pill text-bg-primary">{format!("{:?}", self.current_time)}</span>
-                    <span class="badge rounded-pill text-bg-secondary">{self.current_rate}{"sec./sec."}</span>
-                    <span class={current_block_style}>{self.current_block}{"→"}{self.deadline_block_idx}</span>
+                    <span class="badge rounded-pill text-bg-primary">{format!("{:?}", self.player.current_time)}</span>
+                    <span class="badge rounded-pill text-bg-secondary">{self.player.current_rate}{"sec./sec."}</span>
+                    <span class={current_block_style}>{self.player.current_block}{"→"}{self.player.deadline_block_idx}</span>
+                    <span class="badge rounded-pill text-bg-info">{self.player.controller_kind.name()}</span>
+                    <span class="badge rounded-pill text-bg-light">{estimator::label(self.player.rate_estimate, self.player.target_rate)}</span>
                 </h1>
-                <p>{"Deadline block: "}{format!("{:?}", &(self.subs.blocks[self.deadline_block_idx]))}</p>
-                <p>{"Duration history: "}{format!("{:?}", self.block_timing_history)}</p>
+                if let Some(ref why) = self.player.pacer.rate_problem {
+                    <div class="alert alert-warning">{why}</div>
+                }
+                <p>{format!("Playback rates allowed: {} to {}", self.player.pacer.rate_bounds.min, self.player.pacer.rate_bounds.max)}
+                    if self.player.pacer.idle.is_some() {
+                        {" (waiting for the speaker to catch up)"}
+                    }
+                </p>
+                {replay_status}
+                {script_status}
+                {lint_findings}
+                <p>{"Duration history: "}{format!("{:?}", self.player.block_timing_history)}</p>
+                <p>
+                    {format!("Speaking time on this block: {:.1}s", self.player.speech_clock.elapsed(self.player.clock.now()))}
+                    if let Some(expected) = expected_time {
+                        {format!(", about {expected:.1}s expected")}
+                    }
+                    if let Some(seconds) = autopilot_time {
+                        {format!(", the autopilot moves on after {seconds:.1}s")}
+                    }
+                    if !self.player.speech_clock.is_running() {
+                        {" (clock stopped)"}
+                    }
+                </p>
+                <button class="btn btn-outline-success" onclick={retreat_deadline_block}>{"Back"}</button>
                 <button class="btn btn-success" onclick={advance_deadline_block}>{"Advance deadline..."}</button>
                 <button class="btn btn-primary" onclick={open_aux_window}>{"Open Aux Window"}</button>
+                if self.player.session.is_some() {
+                    <button class="btn btn-outline-secondary" onclick={export_session}>{"Download session log"}</button>
+                }
+                {script_tracks}
+                <label class="form-label">{"Load script from file: "}
+                    <input class="form-control" type="file" accept=".vtt,.srt,.ass,.ssa,.txt" onchange={on_script_file} />
+                </label>
             </div>
         }
     }
-
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            Msg::Periodic => {
-                self.periodic();
+            Msg::ReplayLoaded(result) => {
+                self.replay_session(result.map(|log| *log));
+                return true;
             }
-            Msg::Playing(pl) => {
-                self.is_playing = pl;
-                if pl {
-                    self.current_block_started_at = unixtime();
+            Msg::ExportSession => {
+                let Some(ref events) = self.player.session else {
+                    return false;
+                };
+                let session = SessionLog {
+                    rate_bounds: self.player.browser_rate_bounds,
+                    events: events.clone(),
+                    rates: self.rates_written.borrow().clone(),
+                };
+                if let Err(why) = common::download_json(&session, "session.json") {
+                    log::error!("{why}");
                 }
+                return false;
             }
-            Msg::RateChange => {
-                self.current_rate = self
-                    .video_el
-                    .cast::<HtmlVideoElement>()
-                    .unwrap()
-                    .playback_rate();
-            }
-            Msg::NextDeadline => {
-                let deadline_cue = b2c(&self.subs.blocks[self.deadline_block_idx]);
-                self.deadline_block_idx += 1;
-                let elapsed = unixtime() - self.current_block_started_at;
-                self.current_block_started_at = unixtime();
-                let true_duration = (deadline_cue.end - deadline_cue.start).as_secs_f64();
-                log::info!(
-                    "Latest block was read in {elapsed}, but was supposed to take {true_duration}"
-                );
-                self.block_timing_history.push(true_duration / elapsed);
-
-                // Calculate the target rate by averaging the previous rates.
-                self.target_rate = self.block_timing_history.iter().cloned().sum::<f64>()
-                    / self.block_timing_history.len() as f64;
-            }
-            Msg::NewWindow(w) => {
-                self.child_window = Some(w);
+            // Anything else that arrives during a replay, like the results of fetches started
+            // again by replayed messages, is already in the log.
+            _ if self.replay.is_some() => return false,
+            Msg::NewWindow(ref w) => self.child_window = Some(w.clone()),
+            Msg::ScriptFileChosen(ref file) => {
+                let name = file.name();
+                let cb = ctx.link().callback(move |result| Msg::ScriptLoaded {
+                    track: None,
+                    name: name.clone(),
+                    result,
+                });
+                let name = file.name();
+                self.script_reader =
+                    Some(gloo::file::callbacks::read_as_bytes(file, move |result| {
+                        cb.emit(result.map_err(|e| format!("Could not read {name}: {e}").into()));
+                    }));
             }
-            Msg::ReceivedFromChild(value) => match value {
-                AuxToMainInterop::AdvanceDeadline => ctx.link().send_message(Msg::NextDeadline),
-                AuxToMainInterop::SetIsPlaying(value) => {
-                    let vid: HtmlVideoElement = match self.video_el.cast::<HtmlVideoElement>() {
-                        Some(v) => v,
-                        None => return false,
-                    };
-                    match value {
-                        true => {
-                            let _ = vid.play();
-                        }
-                        false => {
-                            let _ = vid.pause();
-                        }
-                    }
-                }
-                AuxToMainInterop::ResetRate => {
-                    self.target_rate = 1.0;
-                    match self.video_el.cast::<HtmlVideoElement>() {
-                        Some(v) => v.set_playback_rate(1.0),
-                        None => return false,
-                    };
-                    self.block_timing_history.clear();
-                }
-            },
+            Msg::ScriptLoaded { .. } => self.script_reader = None,
+            _ => {}
         }
-        true
+        let Some(event) = SessionEvent::of(&msg) else {
+            return false;
+        };
+        let mut host = PageHost {
+            ctx,
+            video_el: &self.video_el,
+            fade_canvas: &self.fade_canvas,
+            child_window: &self.child_window,
+            rates_written: &self.rates_written,
+            keydown_listener: &mut self.global_keydown_listener,
+        };
+        self.player.update(&mut host, event)
     }
 
     fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
         if first_render {
             // Here we'll set up the global event listener
+            self.global_keydown_listener = Some(keydown_listener(ctx, &self.player.manifest));
             let window = web_sys::window().unwrap();
-            let listener = EventListener::new(&window, "keydown", {
-                common::event_handler(ctx.link().callback(Msg::ReceivedFromChild))
-            });
-            self.global_keydown_listener = Some(listener);
 
             // Also set up the global message listener
             let listener = EventListener::new(&window, "message", {
-                let cb = ctx
-                    .link()
-                    .callback(move |data| Msg::ReceivedFromChild(data));
+                let cb = ctx.link().callback(Msg::ReceivedFromChild);
                 move |e| {
                     let e: MessageEvent = (e.clone()).dyn_into().unwrap();
                     let data: AuxToMainInterop = e.data().into_serde().unwrap_throw();
@@ -218,6 +463,17 @@ impl Component for App {
             });
             self.global_message_listener = Some(listener);
 
+            // Also keep track of the tab being hidden, which makes timing unreliable
+            let document = gloo::utils::document();
+            let visibility_changed = ctx
+                .link()
+                .callback(|_| Msg::VisibilityChanged(gloo::utils::document().hidden()));
+            self.visibility_listener = Some(EventListener::new(
+                &document,
+                "visibilitychange",
+                move |_| visibility_changed.emit(()),
+            ));
+
             // Also set up the global interval
             let periodic = ctx.link().callback(|_| Msg::Periodic);
             self.interval_callback = Some(Interval::new(100, move || {
@@ -251,8 +507,47 @@ impl Component for App {
 }
 
 impl App {
+    /// Feeds a recorded session through the player against the recorded clock and video, and
+    /// compares the playback rates that come out with the recorded ones.
+    fn replay_session(&mut self, result: Result<SessionLog, String>) {
+        let Some(ref mut replay) = self.replay else {
+            return;
+        };
+        let report = result.map(|session| replay.run(&mut self.player, session));
+        match report {
+            Ok(ref report) => {
+                log::info!("{}", report.describe());
+                // Expose the outcome to tooling as `window.replayRates`.
+                let _ = js_sys::Reflect::set(
+                    &gloo::utils::window(),
+                    &JsValue::from_str("replayRates"),
+                    &JsValue::from_serde(report).unwrap_throw(),
+                );
+            }
+            Err(ref why) => log::error!("{why}"),
+        }
+        replay.report = Some(report);
+    }
+}
+
+/// The page, as the player sees it.
+struct PageHost<'a> {
+    ctx: &'a Context<App>,
+    video_el: &'a NodeRef,
+    fade_canvas: &'a NodeRef,
+    child_window: &'a Option<Window>,
+    rates_written: &'a Rc<RefCell<Vec<RateWrite>>>,
+    keydown_listener: &'a mut Option<EventListener>,
+}
+
+impl Host for PageHost<'_> {
+    fn video(&self) -> Option<Rc<dyn VideoSource>> {
+        let element = self.video_el.cast::<HtmlVideoElement>()?;
+        Some(Rc::new(PageVideo::new(element, self.rates_written.clone())))
+    }
+
     fn send_to_child(&self, what: MainToAuxInterop) {
-        if let Some(ref w) = self.child_window {
+        if let Some(w) = self.child_window {
             let origin = gloo::utils::window().origin();
             if let Err(why) = w.post_message(&JsValue::from_serde(&what).unwrap(), &origin) {
                 log::error!(
@@ -263,138 +558,66 @@ impl App {
         }
     }
 
-    fn periodic(&mut self) {
-        let element: HtmlVideoElement;
-        if let Some(v) = self.video_el.get() {
-            element = v.dyn_into().unwrap();
-        } else {
-            return;
-        }
-        let now = Duration::from_secs_f64(element.current_time());
-        self.current_time = now;
-        self.send_to_child(MainToAuxInterop::CurrentStatus {
-            time: element.current_time(),
-            rate: element.playback_rate(),
-            playing: !element.paused(),
-        });
-
-        // Send the context
-        let mut prev = vec![];
-        let mut next = vec![];
-        for i in (self.deadline_block_idx as isize) - 2..self.deadline_block_idx as isize {
-            if i < 0 {
-                continue;
-            }
-            prev.push(b2c(&self.subs.blocks[i as usize]).text.clone())
-        }
-        for i in (self.deadline_block_idx as isize + 1)..(self.deadline_block_idx as isize + 5) {
-            if i as usize >= self.subs.blocks.len() {
-                continue;
+    fn fetch(&self, what: Fetch) {
+        self.ctx.link().send_future(async move {
+            match what {
+                Fetch::Script { track, url } => {
+                    let result = script::fetch_script(&url).await;
+                    Msg::ScriptLoaded {
+                        track: Some(track),
+                        name: url,
+                        result,
+                    }
+                }
+                Fetch::Notes(url) => Msg::NotesLoaded(script::fetch_notes(&url).await),
+                Fetch::Chapters(url) => Msg::ChaptersLoaded(script::fetch_script(&url).await),
             }
-            next.push(b2c(&self.subs.blocks[i as usize]).text.clone())
-        }
-        let ctx = CueContext {
-            current_idx: self.deadline_block_idx as i32,
-            current: b2c(&self.subs.blocks[self.deadline_block_idx]).text.clone(),
-            prev,
-            next,
-        };
-
-        self.send_to_child(MainToAuxInterop::CueContext(ctx));
-
-        let sub_list = &self.subs.blocks;
+        });
+    }
 
-        // Loop over the blocks to find one that the value matches.
-        let idxs = (self.current_block..sub_list.len()).chain(0..self.current_block);
-        for idx in idxs {
-            let cue = b2c(&sub_list[idx]);
-            // If this cue fits, set this as the current block.
-            if fits(now, cue) {
-                self.current_block = idx;
-                self.current_block_has_passed = false;
-                break;
-            }
-            // Otherwise, if now is after the cue,
-            // then this cue is the last visible one.
-            if cue.end < now {
-                self.current_block = idx.max(self.current_block);
-                self.current_block_has_passed = true;
-            }
+    fn show_manifest(&mut self, manifest: &Manifest) {
+        if let Some(ref title) = manifest.title {
+            gloo::utils::document().set_title(title);
         }
+        *self.keydown_listener = Some(keydown_listener(self.ctx, manifest));
+    }
 
-        // Set the playback rate based on the time left until the end of the deadline block.
-        let deadline_block = b2c(&sub_list[self.deadline_block_idx]);
-        let time_until_end = deadline_block.end.checked_sub(now).unwrap_or_default();
-        // let near_curve =
-        //     bezier_rs::Bezier::from_cubic_coordinates(0.0, 0.0, 0.0, 0.25, 1.0, 0.0, 1.0, 1.0);
-        // let far_curve =
-        //     bezier_rs::Bezier::from_cubic_coordinates(1.0, 1.0, 1.6, 1.0, 2.0, 1.5, 2.0, 2.0);
-
-        let rate_fn = |time: Duration| {
-            let time_s = time.as_secs_f64();
-            let deadline_block_duration = (deadline_block.end - deadline_block.start).as_secs_f64();
-            let x = time_s / deadline_block_duration;
-            // let advanced_rate = |x: f64, target_rate: f64| {
-            //     let k = 5.0;
-            //     2.0 * target_rate * (1.0 / (1.0 + std::f64::consts::E.powf(-k * x)) - 0.5)
-            // };
-
-            // If the current block on the screen is not the current deadline block, we seek fast to it.
-            if self.current_block != self.deadline_block_idx {
-                return 2.0 * (time_s / deadline_block_duration) * self.target_rate;
-            }
-
-            let slow_threshold = 0.1;
-
-            // If we're past the deadline, stop entirely.
-            if x < 0.0 {
-                0.0
-            } else if x > slow_threshold {
-                self.target_rate
-            } else {
-                // We're in the zone where we need to start slowing down.
-                self.target_rate * (x / slow_threshold)
-            }
+    fn show_findings(&self, findings: &[Finding]) {
+        // Expose the findings to tooling as `window.scriptLint`.
+        let _ = js_sys::Reflect::set(
+            &gloo::utils::window(),
+            &JsValue::from_str("scriptLint"),
+            &JsValue::from_serde(findings).unwrap_throw(),
+        );
+    }
 
-            // if x > 1.0 {
-            //     far_curve
-            //         .evaluate(bezier_rs::TValue::Parametric((x - 1.0).min(1.0)))
-            //         .y
-            //         * self.target_rate
-            // } else {
-            //     advanced_rate(x, self.target_rate)
-            // }
-
-            // if x > 1.0 {
-            //     return x * advanced_rate(2.0, 1.0);
-            // } else {
-            //     advanced_rate(x, self.target_rate)
-            // }
-            // if x > 1.0 {
-            //     2.0
-            // } else {
-            //     advanced_rate(x, self.target_rate)
-            // }
-        };
+    fn capture_frame(&self) -> bool {
+        let video = self.video();
+        (self.fade_canvas.cast::<HtmlCanvasElement>())
+            .zip(video.as_deref().and_then(|v| v.element()))
+            .is_some_and(|(c, v)| catch_up::capture_frame(&c, v))
+    }
 
-        #[wasm_bindgen]
-        extern "C" {
-            fn try_set_playback_rate(el: &HtmlVideoElement, rate: f64) -> bool;
-        }
-        if !try_set_playback_rate(&element, rate_fn(time_until_end)) {
-            // Playback rate was bad, but we can ignore that.
-        }
+    fn rehearsal_to_follow(&mut self, settings: &AutopilotSettings, script: &str) -> Option<Run> {
+        autopilot::rehearsal_to_follow(settings, script)
     }
-}
 
-fn b2c(b: &Block) -> &Cue {
-    match b {
-        Block::Cue(v) => v,
+    fn save_run(&self, run: Run) {
+        if let Err(why) = rehearsal::save_run(run) {
+            log::error!("{why}");
+        }
     }
 }
 
-fn fits(time: Duration, cue: &Cue) -> bool {
-    return cue.start <= time && time <= cue.end;
+/// Listens for the manifest's key bindings on the main window.
+fn keydown_listener(ctx: &Context<App>, manifest: &Manifest) -> EventListener {
+    let window = web_sys::window().unwrap();
+    EventListener::new(&window, "keydown", {
+        common::event_handler(
+            ctx.link().callback(Msg::ReceivedFromChild),
+            manifest.keys.clone(),
+        )
+    })
 }
 
 fn main() {
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use crate::autopilot::AutopilotSettings;
+use crate::catch_up::CatchUpSettings;
+use crate::cues::ScriptFormat;
+use crate::duration_model::DurationModelSettings;
+use crate::easing::EasingProfile;
+use crate::estimator::EstimatorSettings;
+use crate::idle::IdleMode;
+use crate::lifecycle::LifecycleSettings;
+use crate::lint::LintSettings;
+use crate::pacing::{ControllerKind, PidGains};
+use crate::rate_bounds::RateBounds;
+use crate::script::DEFAULT_SCRIPT_URL;
+use crate::smoothing::SmoothingSettings;
+
+/// Where the video is loaded from if no manifest says otherwise.
+pub const DEFAULT_VIDEO_URL: &str = "/media/vid-rendered.mp4";
+
+/// Describes one talk: the video, its scripts and the settings to play it with.
+///
+/// Every field has a default, so a manifest only needs to mention what differs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Manifest {
+    pub title: Option<String>,
+    pub video: String,
+    pub scripts: Vec<ScriptTrack>,
+    pub pacing: PacingSettings,
+    pub keys: KeyBindings,
+    pub lint: LintSettings,
+    pub lifecycle: LifecycleSettings,
+    pub autopilot: AutopilotSettings,
+    /// Keep a log of the session that can be downloaded and replayed.
+    pub record_session: bool,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            title: None,
+            video: DEFAULT_VIDEO_URL.to_string(),
+            scripts: vec![ScriptTrack {
+                url: DEFAULT_SCRIPT_URL.to_string(),
+                label: None,
+                language: None,
+                format: None,
+                notes: None,
+                chapters: None,
+            }],
+            pacing: PacingSettings::default(),
+            keys: KeyBindings::default(),
+            lint: LintSettings::default(),
+            lifecycle: LifecycleSettings::default(),
+            autopilot: AutopilotSettings::default(),
+            record_session: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScriptTrack {
+    pub url: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Guessed from the URL or the contents if not given.
+    #[serde(default)]
+    pub format: Option<ScriptFormat>,
+    /// URL of a JSON object mapping cue identifiers to presenter notes.
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// URL of a WebVTT chapters track that splits the script into sections.
+    #[serde(default)]
+    pub chapters: Option<String>,
+}
+
+impl ScriptTrack {
+    /// The name to show for this track in the UI.
+    pub fn name(&self) -> &str {
+        self.label
+            .as_deref()
+            .or(self.language.as_deref())
+            .unwrap_or(&self.url)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PacingSettings {
+    /// Which pacing controller to start with.
+    pub controller: ControllerKind,
+    /// Playback rate to start with, before any cues have been timed.
+    pub initial_rate: f64,
+    /// Fraction of the deadline cue at whose end the video starts slowing down.
+    pub slow_threshold: f64,
+    /// How the rate drops to 0 over that last fraction.
+    pub brake: EasingProfile,
+    /// How the rate picks up again once the deadline moves on.
+    pub release: EasingProfile,
+    /// How long picking up again takes; 0 switches to the new rate at once.
+    pub release_seconds: f64,
+    /// Tuning of the `pid` controller.
+    pub pid: PidGains,
+    /// How the speaker's rate is learned from the timing of each cue.
+    pub estimator: EstimatorSettings,
+    /// How long each cue is expected to take from its text, before and alongside measurements.
+    pub duration_model: DurationModelSettings,
+    /// Slowest rate the video plays at; below this it is paused instead.
+    pub min_rate: f64,
+    /// Fastest rate the video plays at; a cue further behind than this can catch up is skipped to.
+    pub max_rate: f64,
+    /// How quickly the rate may change on its way to what the controller asks for.
+    pub smoothing: SmoothingSettings,
+    /// What the video shows while waiting for the speaker, unless the cue says otherwise.
+    pub idle: IdleMode,
+    /// How the video gets to the deadline cue when the speaker is ahead of it.
+    pub catch_up: CatchUpSettings,
+}
+
+impl PacingSettings {
+    pub fn rate_bounds(&self) -> RateBounds {
+        RateBounds {
+            min: self.min_rate,
+            max: self.max_rate,
+        }
+    }
+}
+
+impl Default for PacingSettings {
+    fn default() -> Self {
+        Self {
+            controller: ControllerKind::default(),
+            initial_rate: 1.0,
+            slow_threshold: 0.1,
+            brake: EasingProfile::Linear,
+            release: EasingProfile::Linear,
+            release_seconds: 0.0,
+            pid: PidGains::default(),
+            estimator: EstimatorSettings::default(),
+            duration_model: DurationModelSettings::default(),
+            min_rate: RateBounds::default().min,
+            max_rate: 4.0,
+            smoothing: SmoothingSettings::default(),
+            idle: IdleMode::default(),
+            catch_up: CatchUpSettings::default(),
+        }
+    }
+}
+
+/// Key codes (as in `KeyboardEvent.keyCode`) for each command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub next: Vec<u32>,
+    pub prev: Vec<u32>,
+    pub pause: Vec<u32>,
+    pub play: Vec<u32>,
+    pub cancel: Vec<u32>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            next: vec![34],   // page down
+            prev: vec![33],   // page up
+            pause: vec![27],  // Esc
+            play: vec![91],   // P
+            cancel: vec![66], // B
+        }
+    }
+}
+
+/// Downloads and deserializes the JSON manifest from the given URL.
+pub async fn fetch_manifest(url: &str) -> Result<Manifest, String> {
+    let manifest: Manifest = crate::common::fetch_json(url, "manifest").await?;
+    if manifest.scripts.is_empty() {
+        return Err(format!("Manifest {url} does not list any scripts"));
+    }
+    Ok(manifest)
+}
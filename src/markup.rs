@@ -0,0 +1,264 @@
+use yew::prelude::*;
+
+/// A piece of cue text with its WebVTT markup resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Text(String),
+    Span {
+        kind: SpanKind,
+        /// Classes given as `<c.one.two>`, `<b.one>` etc.
+        classes: Vec<String>,
+        /// The speaker of a `<v>` span, or the language of a `<lang>` span.
+        annotation: String,
+        children: Vec<Node>,
+    },
+}
+
+impl Node {
+    fn kind(&self) -> Option<SpanKind> {
+        match self {
+            Node::Text(_) => None,
+            Node::Span { kind, .. } => Some(*kind),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Bold,
+    Italic,
+    Underline,
+    Voice,
+    Class,
+    Lang,
+    Ruby,
+    RubyText,
+}
+
+impl SpanKind {
+    fn from_tag(name: &str) -> Self {
+        match name {
+            "b" => SpanKind::Bold,
+            "i" => SpanKind::Italic,
+            "u" => SpanKind::Underline,
+            "v" => SpanKind::Voice,
+            "lang" => SpanKind::Lang,
+            "ruby" => SpanKind::Ruby,
+            "rt" => SpanKind::RubyText,
+            // Unknown tags (like SRT's `<font>`) keep their contents and classes.
+            _ => SpanKind::Class,
+        }
+    }
+}
+
+/// Parses cue text into a markup tree.
+///
+/// Unclosed tags end with the cue, stray end tags and timestamp tags are dropped.
+pub fn parse(text: &str) -> Vec<Node> {
+    // Spans that have been opened but not closed yet, innermost last.
+    let mut stack: Vec<Node> = vec![];
+    let mut root = vec![];
+    let mut rest = text;
+
+    fn push(stack: &mut [Node], root: &mut Vec<Node>, node: Node) {
+        match stack.last_mut() {
+            Some(Node::Span { children, .. }) => children.push(node),
+            _ => root.push(node),
+        }
+    }
+
+    while !rest.is_empty() {
+        let (before, tag, after) = match rest.split_once('<') {
+            Some((before, after)) => match after.split_once('>') {
+                Some((tag, after)) => (before, Some(tag), after),
+                None => (rest, None, ""),
+            },
+            None => (rest, None, ""),
+        };
+        if !before.is_empty() {
+            push(&mut stack, &mut root, Node::Text(decode_entities(before)));
+        }
+        rest = after;
+
+        let Some(tag) = tag else { continue };
+        if let Some(name) = tag.strip_prefix('/') {
+            let kind = SpanKind::from_tag(name.trim());
+            if !stack.iter().any(|n| n.kind() == Some(kind)) {
+                continue;
+            }
+            // Close everything up to and including the matching span.
+            while let Some(node) = stack.pop() {
+                let done = node.kind() == Some(kind);
+                push(&mut stack, &mut root, node);
+                if done {
+                    break;
+                }
+            }
+        } else if tag.starts_with(|c: char| c.is_ascii_digit()) {
+            // A timestamp tag for karaoke-style display, which the presenter view does not need.
+        } else {
+            let (name, annotation) = tag.split_once([' ', '\t']).unwrap_or((tag, ""));
+            let mut parts = name.split('.');
+            stack.push(Node::Span {
+                kind: SpanKind::from_tag(parts.next().unwrap_or_default()),
+                classes: parts
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                annotation: decode_entities(annotation.trim()),
+                children: vec![],
+            });
+        }
+    }
+
+    while let Some(node) = stack.pop() {
+        push(&mut stack, &mut root, node);
+    }
+    root
+}
+
+/// Returns the cue text with all markup removed.
+pub fn plain_text(text: &str) -> String {
+    fn collect(nodes: &[Node], out: &mut String) {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Span { children, .. } => collect(children, out),
+            }
+        }
+    }
+    let mut out = String::new();
+    collect(&parse(text), &mut out);
+    out
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&lrm;", "\u{200e}")
+        .replace("&rlm;", "\u{200f}")
+        .replace("&amp;", "&")
+}
+
+/// Renders cue text with its markup: emphasis, speaker labels and `cue-<class>` CSS classes.
+pub fn render(text: &str) -> Html {
+    render_nodes(&parse(text))
+}
+
+fn render_nodes(nodes: &[Node]) -> Html {
+    nodes.iter().map(render_node).collect()
+}
+
+fn render_node(node: &Node) -> Html {
+    match node {
+        Node::Text(text) => html!({ text }),
+        Node::Span {
+            kind,
+            classes,
+            annotation,
+            children,
+        } => {
+            let class = classes
+                .iter()
+                .map(|c| format!("cue-{c}"))
+                .collect::<Classes>();
+            let children = render_nodes(children);
+            match kind {
+                SpanKind::Bold => html!(<b {class}>{children}</b>),
+                SpanKind::Italic => html!(<i {class}>{children}</i>),
+                SpanKind::Underline => html!(<u {class}>{children}</u>),
+                SpanKind::Voice => html! {
+                    <span {class}>
+                        if !annotation.is_empty() {
+                            <span class="badge text-bg-secondary me-1">{annotation}</span>
+                        }
+                        {children}
+                    </span>
+                },
+                SpanKind::Lang => html!(<span {class} lang={annotation.clone()}>{children}</span>),
+                SpanKind::Ruby => html!(<ruby {class}>{children}</ruby>),
+                SpanKind::RubyText => html!(<rt {class}>{children}</rt>),
+                SpanKind::Class => html!(<span {class}>{children}</span>),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(t: &str) -> Node {
+        Node::Text(t.to_string())
+    }
+
+    fn span(kind: SpanKind, children: Vec<Node>) -> Node {
+        Node::Span {
+            kind,
+            classes: vec![],
+            annotation: String::new(),
+            children,
+        }
+    }
+
+    #[test]
+    fn parses_nested_spans_with_classes_and_annotations() {
+        let nodes = parse("<v.loud Ann>Hi <b>there</b></v>!");
+        assert_eq!(
+            nodes,
+            vec![
+                Node::Span {
+                    kind: SpanKind::Voice,
+                    classes: vec!["loud".to_string()],
+                    annotation: "Ann".to_string(),
+                    children: vec![text("Hi "), span(SpanKind::Bold, vec![text("there")])],
+                },
+                text("!"),
+            ]
+        );
+    }
+
+    #[test]
+    fn closes_unclosed_tags_at_the_end_of_the_cue() {
+        assert_eq!(
+            parse("<i>one <b>two"),
+            vec![span(
+                SpanKind::Italic,
+                vec![text("one "), span(SpanKind::Bold, vec![text("two")])]
+            )]
+        );
+    }
+
+    #[test]
+    fn closing_an_outer_tag_closes_the_inner_ones() {
+        assert_eq!(
+            parse("<i>a<b>b</i>c"),
+            vec![
+                span(
+                    SpanKind::Italic,
+                    vec![text("a"), span(SpanKind::Bold, vec![text("b")])]
+                ),
+                text("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_stray_end_tags_and_timestamps() {
+        assert_eq!(plain_text("a</b>b<00:00:01.000>c"), "abc");
+    }
+
+    #[test]
+    fn keeps_a_lone_angle_bracket_as_text() {
+        assert_eq!(plain_text("a < b"), "a < b");
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            plain_text("&lt;b&gt; &amp;lt; a&nbsp;b"),
+            "<b> &lt; a\u{a0}b"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use crate::directives::CueDirectives;
+use crate::idle::{Idle, IdleMode};
+use crate::pacing::{PacingController, PacingInput};
+use crate::rate_bounds::RateBounds;
+use crate::smoothing::RateSmoother;
+use crate::sources::VideoSource;
+
+/// The pacing step: turns where the talk is into a playback rate and gives it to the video.
+///
+/// It sees the video only as a [`VideoSource`] and the time only through [`PacingInput`], so it
+/// runs the same against the page and against a replay.
+pub struct Pacer {
+    pub controller: Box<dyn PacingController>,
+    pub smoother: RateSmoother,
+    /// The rates the video may actually be given: the manifest's limits within the browser's.
+    pub rate_bounds: RateBounds,
+    /// Set while the video waits for the speaker because the controller wanted a rate too slow
+    /// to play at.
+    pub idle: Option<Idle>,
+    /// The last still image faded to while idle, kept around to fade back out.
+    pub still_image: Option<String>,
+    /// Why the last playback rate could not be set as asked, if it couldn't.
+    pub rate_problem: Option<String>,
+}
+
+impl Pacer {
+    pub fn new(controller: Box<dyn PacingController>, rate_bounds: RateBounds) -> Self {
+        Self {
+            controller,
+            smoother: RateSmoother::default(),
+            rate_bounds,
+            idle: None,
+            still_image: None,
+            rate_problem: None,
+        }
+    }
+
+    /// Sets the playback rate for the moment described by `input`.
+    ///
+    /// The `directives` of the cue on screen may override the controller, and the rate never
+    /// goes above `max_rate`. When it has to drop below what the video plays at, the video shows
+    /// `idle_mode` until the rate picks up again, and when it has to go above, the video skips to
+    /// the deadline cue.
+    pub fn step(
+        &mut self,
+        video: &dyn VideoSource,
+        input: &PacingInput,
+        directives: &CueDirectives,
+        max_rate: f64,
+        idle_mode: &IdleMode,
+    ) {
+        let rate = self.controller.rate(input);
+        let rate = directives.constrain(rate, input.target_rate).min(max_rate);
+        let deadline_start = (input.current_block != input.deadline_idx)
+            .then(|| input.deadline().start.as_secs_f64());
+        self.apply_rate(
+            video,
+            rate,
+            directives.rate.is_some(),
+            input.wall_time,
+            idle_mode,
+            deadline_start,
+        );
+    }
+
+    /// Gives the video the requested rate, smoothed unless it is `fixed` by the cue, falling back
+    /// to idling when it is too slow and to seeking to `deadline_start`, if the video is behind
+    /// the deadline cue, when it is too fast.
+    fn apply_rate(
+        &mut self,
+        video: &dyn VideoSource,
+        requested: f64,
+        fixed: bool,
+        now: f64,
+        idle_mode: &IdleMode,
+        deadline_start: Option<f64>,
+    ) {
+        let bounds = self.rate_bounds;
+        if let Some(start) = deadline_start.filter(|_| requested > bounds.max) {
+            log::info!("Too far behind to catch up, skipping to the deadline cue");
+            // The skip ends any idling, and the video goes on from where it lands.
+            if self.idle.take().is_some() {
+                video.play();
+            }
+            video.set_current_time(start);
+        }
+
+        let rate = match fixed {
+            true => self.smoother.reset_to(requested.min(bounds.max), now),
+            false => self.smoother.smooth(requested.min(bounds.max), now),
+        };
+        if rate < bounds.min {
+            match self.idle {
+                Some(ref idle) => idle.tick(video),
+                None if !video.paused() => {
+                    if let IdleMode::Still(ref url) = idle_mode {
+                        self.still_image = Some(url.clone());
+                    }
+                    self.idle = Some(Idle::start(idle_mode.clone(), video));
+                }
+                None => {}
+            }
+            return;
+        }
+        if let Some(idle) = self.idle.take() {
+            idle.leave(video);
+            video.play();
+        }
+
+        if !self.smoother.worth_writing(rate, video.playback_rate()) {
+            return;
+        }
+        self.rate_problem = video.set_rate(rate).err();
+        if let Some(ref why) = self.rate_problem {
+            log::error!("{why}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::cues::Cue;
+    use crate::pacing::ControllerKind;
+    use crate::sources::{RateWrite, ReplayVideo, VideoState};
+
+    fn cue(start: u64, end: u64) -> Cue {
+        Cue {
+            start: Duration::from_secs(start),
+            end: Duration::from_secs(end),
+            ..Default::default()
+        }
+    }
+
+    fn video(time: f64) -> (ReplayVideo, Rc<RefCell<Vec<RateWrite>>>) {
+        let rates = Rc::new(RefCell::new(vec![]));
+        let video = ReplayVideo::new(rates.clone());
+        video.show(Some(VideoState {
+            time,
+            rate: 1.0,
+            paused: false,
+        }));
+        (video, rates)
+    }
+
+    /// A pacer that may play between half and twice the speed, as the manifest asks within what
+    /// the browser was found to accept.
+    fn pacer() -> Pacer {
+        let manifest = RateBounds { min: 0.5, max: 4.0 };
+        let probed = RateBounds {
+            min: 0.0625,
+            max: 2.0,
+        };
+        Pacer::new(
+            ControllerKind::Constant.build(&Default::default()),
+            manifest.within(probed),
+        )
+    }
+
+    /// Asks for a fixed `rate` with the video in cue `current_block` and the speaker in
+    /// `deadline_idx`, waiting with `idle` if it has to.
+    fn step(
+        pacer: &mut Pacer,
+        video: &ReplayVideo,
+        rate: f64,
+        current_block: usize,
+        deadline_idx: usize,
+        idle: &IdleMode,
+    ) {
+        let cues = [cue(0, 10), cue(10, 20), cue(20, 30)];
+        let input = PacingInput {
+            now: Duration::from_secs_f64(video.current_time()),
+            cues: &cues,
+            current_block,
+            deadline_idx,
+            target_rate: 1.0,
+            wall_time: 0.0,
+            deadline_elapsed: 0.0,
+        };
+        let fixed = CueDirectives {
+            rate: Some(rate),
+            ..Default::default()
+        };
+        pacer.step(video, &input, &fixed, f64::INFINITY, idle);
+    }
+
+    #[test]
+    fn clamps_fast_rates_to_the_browser_and_skips_to_the_deadline_cue() {
+        let mut pacer = pacer();
+        let (video, rates) = video(5.0);
+        step(&mut pacer, &video, 3.0, 0, 0, &IdleMode::Freeze);
+        assert_eq!(video.playback_rate(), 2.0);
+        assert_eq!(
+            video.current_time(),
+            5.0,
+            "the video is in the deadline cue"
+        );
+
+        step(&mut pacer, &video, 3.0, 0, 2, &IdleMode::Freeze);
+        assert_eq!(video.current_time(), 20.0);
+        assert_eq!(
+            rates.borrow().len(),
+            1,
+            "the rate was already at the maximum"
+        );
+    }
+
+    #[test]
+    fn waits_below_the_minimum_rate_and_plays_on_above_it() {
+        let mut pacer = pacer();
+        let (video, rates) = video(9.5);
+        step(&mut pacer, &video, 0.25, 0, 0, &IdleMode::Freeze);
+        assert!(pacer.idle.is_some());
+        assert!(video.paused());
+        assert!(rates.borrow().is_empty());
+
+        step(&mut pacer, &video, 0.75, 0, 0, &IdleMode::Freeze);
+        assert!(pacer.idle.is_none());
+        assert!(!video.paused());
+        assert_eq!(video.playback_rate(), 0.75);
+    }
+
+    #[test]
+    fn skipping_ahead_ends_the_wait() {
+        let mut pacer = pacer();
+        let (video, _) = video(9.5);
+        let hold = IdleMode::Hold(2.0);
+        step(&mut pacer, &video, 0.25, 0, 0, &hold);
+        assert!(video.paused());
+        assert_eq!(video.current_time(), 2.0);
+
+        step(&mut pacer, &video, 3.0, 0, 1, &hold);
+        assert!(pacer.idle.is_none());
+        assert!(!video.paused());
+        assert_eq!(video.current_time(), 10.0);
+        assert_eq!(video.playback_rate(), 2.0);
+    }
+}
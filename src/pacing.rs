@@ -0,0 +1,451 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cues::Cue;
+use crate::easing::EasingProfile;
+use crate::manifest::PacingSettings;
+
+/// Everything a pacing controller gets to see when choosing the playback rate.
+pub struct PacingInput<'a> {
+    /// Current position of the video.
+    pub now: Duration,
+    pub cues: &'a [Cue],
+    /// The cue currently on screen.
+    pub current_block: usize,
+    /// The cue the speaker is reading, which the video must not run past.
+    pub deadline_idx: usize,
+    /// The estimated ratio of video time to speaking time.
+    pub target_rate: f64,
+    /// Wall-clock time in seconds, for controllers that change the rate over time.
+    pub wall_time: f64,
+    /// Seconds the speaker has spent reading the deadline cue, not counting pauses.
+    pub deadline_elapsed: f64,
+}
+
+impl PacingInput<'_> {
+    pub fn deadline(&self) -> &Cue {
+        &self.cues[self.deadline_idx]
+    }
+}
+
+/// A policy for choosing the playback rate, called every time the video position is checked.
+pub trait PacingController {
+    /// Returns the playback rate the video should have right now.
+    fn rate(&mut self, input: &PacingInput) -> f64;
+}
+
+/// The available pacing controllers, as named in the manifest and the presenter view.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ControllerKind {
+    /// Play at the estimated rate, and brake near the end of the deadline cue.
+    #[default]
+    Threshold,
+    /// Always play at the estimated rate, without ever stopping at the deadline.
+    Constant,
+    /// Track the estimated position of the speaker with a PID feedback loop.
+    Pid,
+}
+
+impl ControllerKind {
+    pub const ALL: &'static [ControllerKind] = &[
+        ControllerKind::Threshold,
+        ControllerKind::Constant,
+        ControllerKind::Pid,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ControllerKind::Threshold => "threshold",
+            ControllerKind::Constant => "constant",
+            ControllerKind::Pid => "pid",
+        }
+    }
+
+    pub fn build(self, settings: &PacingSettings) -> Box<dyn PacingController> {
+        match self {
+            ControllerKind::Threshold => Box::new(ThresholdController::new(settings)),
+            ControllerKind::Constant => Box::new(ConstantController),
+            ControllerKind::Pid => Box::new(PidController::new(settings.pid)),
+        }
+    }
+}
+
+/// Seeks fast towards the deadline cue, then plays at the estimated rate until the last
+/// `slow_threshold` fraction of the cue, where the rate drops to 0 along the `brake` curve.
+///
+/// When the deadline moves on, the rate eases from wherever it was to the new rate along the
+/// `release` curve over `release_seconds`.
+pub struct ThresholdController {
+    pub slow_threshold: f64,
+    pub brake: EasingProfile,
+    pub release: EasingProfile,
+    pub release_seconds: f64,
+    last_deadline: Option<usize>,
+    last_rate: f64,
+    /// Wall-clock time of the last deadline change, and the rate at that moment.
+    released: Option<(f64, f64)>,
+}
+
+impl ThresholdController {
+    pub fn new(settings: &PacingSettings) -> Self {
+        Self {
+            slow_threshold: settings.slow_threshold,
+            brake: settings.brake,
+            release: settings.release,
+            release_seconds: settings.release_seconds,
+            last_deadline: None,
+            last_rate: settings.initial_rate,
+            released: None,
+        }
+    }
+
+    fn braking_rate(&self, input: &PacingInput) -> f64 {
+        let deadline_block = input.deadline();
+        let time_s = deadline_block
+            .end
+            .checked_sub(input.now)
+            .unwrap_or_default()
+            .as_secs_f64();
+        // Zero-length cues are reported by the script check, but must not divide by zero here.
+        let deadline_block_duration = deadline_block.duration().as_secs_f64().max(1e-3);
+        let x = time_s / deadline_block_duration;
+
+        // If the current block on the screen is not the current deadline block, we seek fast to it.
+        if input.current_block != input.deadline_idx {
+            return 2.0 * (time_s / deadline_block_duration) * input.target_rate;
+        }
+
+        // If we're past the deadline, stop entirely.
+        if x < 0.0 {
+            0.0
+        } else if x > self.slow_threshold {
+            input.target_rate
+        } else {
+            // We're in the zone where we need to start slowing down.
+            let progress = 1.0 - x / self.slow_threshold;
+            input.target_rate * (1.0 - self.brake.ease(progress))
+        }
+    }
+}
+
+impl PacingController for ThresholdController {
+    fn rate(&mut self, input: &PacingInput) -> f64 {
+        if self.last_deadline.is_some_and(|d| d != input.deadline_idx) {
+            self.released = Some((input.wall_time, self.last_rate));
+        }
+        self.last_deadline = Some(input.deadline_idx);
+
+        let mut rate = self.braking_rate(input);
+        if let Some((released_at, from)) = self.released {
+            let progress = (input.wall_time - released_at) / self.release_seconds;
+            if progress < 1.0 {
+                rate = from + (rate - from) * self.release.ease(progress);
+            } else {
+                self.released = None;
+            }
+        }
+        self.last_rate = rate;
+        rate
+    }
+}
+
+/// Plays at the estimated rate no matter where the deadline is.
+pub struct ConstantController;
+
+impl PacingController for ConstantController {
+    fn rate(&mut self, input: &PacingInput) -> f64 {
+        input.target_rate
+    }
+}
+
+/// Gains for [`PidController`], in playback rate per second of video position error.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct PidGains {
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+    /// Bound on the accumulated error, in video-seconds times seconds, against integral windup.
+    pub max_integral: f64,
+    /// Time constant of the final approach to the end of the cue: the rate never exceeds the
+    /// remaining video time divided by this, so the video glides to a halt.
+    pub settle_seconds: f64,
+}
+
+impl Default for PidGains {
+    fn default() -> Self {
+        Self {
+            kp: 0.5,
+            ki: 0.05,
+            kd: 0.1,
+            max_integral: 5.0,
+            settle_seconds: 1.0,
+        }
+    }
+}
+
+/// Predicts that the speaker finishes the deadline cue after `duration / target_rate` seconds of
+/// speaking, and steers the video to be at the matching point of the cue at every moment.
+///
+/// The error is the estimated speaker position minus the video position, both in video time.
+/// While the speaker is predicted to still be reading, the estimated rate is fed forward and the
+/// PID terms correct for drift. Near the end of the cue the rate is capped so that the video glides
+/// towards the end rather than stopping dead, and comes to rest there if the speaker runs long.
+pub struct PidController {
+    pub gains: PidGains,
+    deadline: Option<usize>,
+    integral: f64,
+    last_error: Option<(f64, f64)>,
+}
+
+impl PidController {
+    pub fn new(gains: PidGains) -> Self {
+        Self {
+            gains,
+            deadline: None,
+            integral: 0.0,
+            last_error: None,
+        }
+    }
+}
+
+impl PacingController for PidController {
+    fn rate(&mut self, input: &PacingInput) -> f64 {
+        if self.deadline != Some(input.deadline_idx) {
+            self.deadline = Some(input.deadline_idx);
+            self.integral = 0.0;
+            self.last_error = None;
+        }
+
+        let cue = input.deadline();
+        let video = input.now.as_secs_f64();
+        let end = cue.end.as_secs_f64();
+        if video >= end {
+            return 0.0;
+        }
+
+        let read = input.deadline_elapsed * input.target_rate;
+        let speaking = read < cue.duration().as_secs_f64();
+        let speaker = (cue.start.as_secs_f64() + read).min(end);
+        let error = speaker - video;
+
+        let derivative = match self.last_error {
+            Some((at, last)) if input.wall_time > at => {
+                let dt = input.wall_time - at;
+                self.integral = (self.integral + error * dt)
+                    .clamp(-self.gains.max_integral, self.gains.max_integral);
+                (error - last) / dt
+            }
+            _ => 0.0,
+        };
+        self.last_error = Some((input.wall_time, error));
+
+        let feed_forward = if speaking { input.target_rate } else { 0.0 };
+        let rate = feed_forward
+            + self.gains.kp * error
+            + self.gains.ki * self.integral
+            + self.gains.kd * derivative;
+        let settle = (end - video) / self.gains.settle_seconds.max(1e-3);
+        rate.min(settle).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: f64, end: f64) -> Cue {
+        Cue {
+            start: Duration::from_secs_f64(start),
+            end: Duration::from_secs_f64(end),
+            ..Default::default()
+        }
+    }
+
+    fn input(cues: &[Cue], now: f64, current_block: usize, deadline_idx: usize) -> PacingInput<'_> {
+        PacingInput {
+            now: Duration::from_secs_f64(now),
+            cues,
+            current_block,
+            deadline_idx,
+            target_rate: 1.5,
+            wall_time: 0.0,
+            deadline_elapsed: 0.0,
+        }
+    }
+
+    fn rate(controller: &mut dyn PacingController, now: f64, current_block: usize) -> f64 {
+        let cues = [cue(0.0, 10.0), cue(10.0, 20.0)];
+        controller.rate(&input(&cues, now, current_block, 1))
+    }
+
+    fn threshold() -> ThresholdController {
+        ThresholdController::new(&PacingSettings::default())
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn threshold_plays_at_the_target_rate_before_braking() {
+        let mut controller = threshold();
+        assert_close(rate(&mut controller, 10.0, 1), 1.5);
+        assert_close(rate(&mut controller, 18.5, 1), 1.5);
+    }
+
+    #[test]
+    fn threshold_brakes_linearly_within_the_slow_threshold() {
+        let mut controller = threshold();
+        assert_close(rate(&mut controller, 19.0, 1), 1.5);
+        assert_close(rate(&mut controller, 19.5, 1), 0.75);
+        assert_close(rate(&mut controller, 19.9, 1), 0.15);
+        assert_close(rate(&mut controller, 20.0, 1), 0.0);
+        assert_close(rate(&mut controller, 25.0, 1), 0.0);
+    }
+
+    #[test]
+    fn threshold_brakes_along_the_brake_curve() {
+        let mut controller = threshold();
+        controller.brake = EasingProfile::EaseOut;
+        let braked = rate(&mut controller, 19.5, 1);
+        assert_close(braked, 1.5 * (1.0 - EasingProfile::EaseOut.ease(0.5)));
+        assert!(braked < 0.75);
+    }
+
+    #[test]
+    fn threshold_catches_up_in_proportion_to_the_remaining_deadline() {
+        let mut controller = threshold();
+        // 2.0 * (time_s / duration) * target_rate
+        assert_close(rate(&mut controller, 5.0, 0), 2.0 * (15.0 / 10.0) * 1.5);
+        assert_close(rate(&mut controller, 9.0, 0), 2.0 * (11.0 / 10.0) * 1.5);
+    }
+
+    #[test]
+    fn threshold_releases_over_release_seconds_when_the_deadline_moves() {
+        let mut controller = threshold();
+        controller.release_seconds = 2.0;
+        let cues = [cue(0.0, 10.0), cue(10.0, 20.0)];
+        let at = |wall_time, deadline_idx| PacingInput {
+            wall_time,
+            ..input(&cues, 10.0, deadline_idx, deadline_idx)
+        };
+        // Stopped at the end of the first cue.
+        let stopped = PacingInput {
+            now: Duration::from_secs(10),
+            ..at(0.0, 0)
+        };
+        assert_close(controller.rate(&stopped), 0.0);
+        // The deadline moves on: ease from 0 back to the target rate over two seconds.
+        assert_close(controller.rate(&at(1.0, 1)), 0.0);
+        assert_close(controller.rate(&at(2.0, 1)), 0.75);
+        assert_close(controller.rate(&at(3.0, 1)), 1.5);
+        assert_close(controller.rate(&at(4.0, 1)), 1.5);
+    }
+
+    #[test]
+    fn threshold_switches_at_once_without_release_seconds() {
+        let mut controller = threshold();
+        let cues = [cue(0.0, 10.0), cue(10.0, 20.0)];
+        assert_close(controller.rate(&input(&cues, 10.0, 0, 0)), 0.0);
+        assert_close(controller.rate(&input(&cues, 10.0, 1, 1)), 1.5);
+    }
+
+    #[test]
+    fn constant_ignores_the_deadline() {
+        assert_close(rate(&mut ConstantController, 5.0, 0), 1.5);
+        assert_close(rate(&mut ConstantController, 25.0, 1), 1.5);
+    }
+
+    /// Steps a PID controller through the whole cue at 10 Hz, moving the video at the returned
+    /// rate, with the speaker reading at `target_rate` from wall time 0.
+    fn run_pid(gains: PidGains, video_start: f64, seconds: f64) -> Vec<(f64, f64)> {
+        let cues = [cue(0.0, 10.0), cue(10.0, 20.0)];
+        let mut controller = PidController::new(gains);
+        let mut video = video_start;
+        let mut trace = Vec::new();
+        for step in 0..=(seconds * 10.0) as usize {
+            let wall_time = step as f64 / 10.0;
+            let rate = controller.rate(&PacingInput {
+                wall_time,
+                deadline_elapsed: wall_time,
+                ..input(&cues, video, 1, 1)
+            });
+            trace.push((wall_time, video));
+            video += rate * 0.1;
+        }
+        trace
+    }
+
+    #[test]
+    fn pid_arrives_at_the_end_of_the_cue_when_the_speaker_is_predicted_to_finish() {
+        // Ten seconds of video at 1.5 times the speaking speed: the speaker finishes at 6.67 s.
+        let predicted_finish = 10.0 / 1.5;
+        let trace = run_pid(PidGains::default(), 10.0, 12.0);
+        let position_at = |t: f64| {
+            trace
+                .iter()
+                .find(|(wall_time, _)| *wall_time >= t - 1e-9)
+                .unwrap()
+                .1
+        };
+        // Midway through, the video is where the speaker is predicted to be.
+        assert!((position_at(predicted_finish / 2.0) - 15.0).abs() < 0.2);
+        // It glides into the end of the cue around the predicted finish and never runs past it.
+        assert!(position_at(predicted_finish) > 19.0);
+        assert!(position_at(predicted_finish + 3.0) > 19.9);
+        assert!(trace.iter().all(|(_, video)| *video <= 20.0));
+    }
+
+    #[test]
+    fn pid_catches_up_when_the_video_starts_behind() {
+        let trace = run_pid(PidGains::default(), 9.0, 4.0);
+        // After four seconds the speaker is at 16 s of video.
+        assert!((trace.last().unwrap().1 - 16.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn pid_clamps_the_integral() {
+        let cues = [cue(0.0, 10.0), cue(10.0, 20.0)];
+        let mut controller = PidController::new(PidGains {
+            max_integral: 2.0,
+            ..PidGains::default()
+        });
+        // The video is stuck at the start while the speaker reads on.
+        for step in 0..100 {
+            controller.rate(&PacingInput {
+                wall_time: step as f64 / 10.0,
+                deadline_elapsed: step as f64 / 10.0,
+                ..input(&cues, 10.0, 1, 1)
+            });
+        }
+        assert_close(controller.integral, 2.0);
+    }
+
+    #[test]
+    fn pid_waits_at_the_end_when_the_speaker_runs_long() {
+        // The speaker is predicted to finish after 6.67 s but is still reading at 20 s.
+        let trace = run_pid(PidGains::default(), 10.0, 20.0);
+        let (_, at_15) = trace[150];
+        let (_, at_20) = trace[200];
+        assert!(at_15 > 19.99);
+        assert!(at_20 - at_15 < 1e-3);
+        assert!(at_20 <= 20.0);
+    }
+
+    #[test]
+    fn pid_stops_at_the_end_of_the_cue() {
+        let cues = [cue(0.0, 10.0), cue(10.0, 20.0)];
+        let mut controller = PidController::new(PidGains::default());
+        let at_end = PacingInput {
+            wall_time: 20.0,
+            ..input(&cues, 20.0, 1, 1)
+        };
+        assert_close(controller.rate(&at_end), 0.0);
+    }
+}
@@ -0,0 +1,1121 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::autopilot::{Autopilot, AutopilotSettings};
+use crate::aux::{AuxToMainInterop, CueContext, MainToAuxInterop};
+use crate::catch_up::{CatchUpStrategy, Crossfade};
+use crate::cues::{self, Cue, Script};
+use crate::duration_model::DurationModel;
+use crate::estimator::{self, Estimate};
+use crate::lifecycle::{Phase, Summary};
+use crate::lint::{self, Finding};
+use crate::manifest::Manifest;
+use crate::pacer::Pacer;
+use crate::pacing::{ControllerKind, PacingInput};
+use crate::rate_bounds::RateBounds;
+use crate::rehearsal::{CueTiming, Run};
+use crate::script::{self, ScriptError};
+use crate::session::{LoggedEvent, SessionEvent};
+use crate::smoothing::RateSmoother;
+use crate::sources::{Clock, VideoSource, VideoState};
+use crate::speech_clock::SpeechClock;
+
+/// What the player needs from the page around it, so that a replay can stand in for the page.
+pub trait Host {
+    /// The video pacing acts on, once there is one.
+    fn video(&self) -> Option<Rc<dyn VideoSource>>;
+    /// Passes a message on to the aux window, if it is open.
+    fn send_to_child(&self, what: MainToAuxInterop);
+    /// Starts loading something, whose result comes back as an event.
+    fn fetch(&self, what: Fetch);
+    /// Applies what the manifest says about the page itself, like its title and key bindings.
+    fn show_manifest(&mut self, manifest: &Manifest);
+    /// Makes the findings of the script check available to tooling.
+    fn show_findings(&self, findings: &[Finding]);
+    /// Covers the video with its current frame, to fade from after a cut; returns whether that
+    /// worked.
+    fn capture_frame(&self) -> bool;
+    /// The saved rehearsal for the autopilot to follow.
+    fn rehearsal_to_follow(&mut self, settings: &AutopilotSettings, script: &str) -> Option<Run>;
+    /// Keeps a recorded rehearsal.
+    fn save_run(&self, run: Run);
+}
+
+/// Something for the [`Host`] to load.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fetch {
+    /// A script track of the manifest, answered by [`SessionEvent::ScriptLoaded`].
+    Script { track: usize, url: String },
+    /// Answered by [`SessionEvent::NotesLoaded`].
+    Notes(String),
+    /// Answered by [`SessionEvent::ChaptersLoaded`].
+    Chapters(String),
+}
+
+/// The deadline moving on from a cue, as the speaker finishes it or jumps elsewhere.
+struct Advance {
+    cue: usize,
+    /// The cue the deadline moved to, which is `cue` itself after the last one.
+    to: usize,
+    /// Active speaking time spent on the cue.
+    seconds: f64,
+    /// Whether the timing went into `block_timing_history`.
+    sampled: bool,
+}
+
+/// The talk as it goes: the script, where the video and the speaker are in it, and the pacing
+/// that keeps them together.
+///
+/// It reaches the page only through a [`Host`] and the time only through its [`Clock`], so a
+/// recorded session can be fed through it again.
+pub struct Player {
+    pub manifest: Manifest,
+    pub script_track: usize,
+    pub subs: Option<Script>,
+    pub load_error: Option<ScriptError>,
+    pub lint_findings: Vec<Finding>,
+    pub current_block: usize,
+    pub current_block_has_passed: bool,
+    pub is_playing: bool,
+    pub current_time: Duration,
+    pub current_rate: f64,
+    pub deadline_block_idx: usize,
+    pub block_timing_history: Vec<f64>,
+    /// Every advance of the deadline, so that going back can undo it.
+    advances: Vec<Advance>,
+    pub phase: Phase,
+    /// Wall-clock times at which the talk started and finished.
+    started_at: f64,
+    finished_at: f64,
+    /// Active speaking time over all finished cues.
+    speaking_total: f64,
+    /// The run being recorded, in rehearsal mode.
+    pub rehearsal: Option<Run>,
+    /// Set while the deadline advances on its own instead of following the presenter.
+    pub autopilot: Option<Autopilot>,
+    pub speech_clock: SpeechClock,
+    pub target_rate: f64,
+    pub rate_estimate: Option<Estimate>,
+    /// Expected speaking time of each cue, from the language of the loaded script.
+    pub duration_model: DurationModel,
+    pub controller_kind: ControllerKind,
+    /// The rates the browser accepts, found out at startup.
+    pub browser_rate_bounds: RateBounds,
+    pub pacer: Pacer,
+    pub crossfade: Option<Crossfade>,
+    /// Where the time comes from: the browser's clock, or the recorded one during a replay.
+    pub clock: Rc<dyn Clock>,
+    /// Every event so far, to save the session and replay it later, if the manifest asks for it.
+    pub session: Option<Vec<LoggedEvent>>,
+}
+
+impl Player {
+    /// A player that has yet to get its manifest, and records the session if `record` is set
+    /// until the manifest says otherwise.
+    pub fn new(clock: Rc<dyn Clock>, browser_rate_bounds: RateBounds, record: bool) -> Self {
+        Self {
+            manifest: Manifest::default(),
+            script_track: 0,
+            subs: None,
+            load_error: None,
+            lint_findings: vec![],
+            current_block: 0,
+            current_block_has_passed: false,
+            is_playing: false,
+            current_time: Duration::ZERO,
+            current_rate: 1.0,
+            deadline_block_idx: 0,
+            block_timing_history: vec![],
+            advances: vec![],
+            phase: Phase::NotStarted,
+            started_at: 0.0,
+            finished_at: 0.0,
+            speaking_total: 0.0,
+            rehearsal: None,
+            autopilot: None,
+            speech_clock: SpeechClock::default(),
+            target_rate: 1.0,
+            rate_estimate: None,
+            duration_model: DurationModel::default(),
+            controller_kind: ControllerKind::default(),
+            browser_rate_bounds,
+            pacer: Pacer::new(
+                ControllerKind::default().build(&Default::default()),
+                browser_rate_bounds,
+            ),
+            crossfade: None,
+            clock,
+            session: record.then(Vec::new),
+        }
+    }
+
+    /// Logs the event, if the session is recorded, and acts on it.
+    pub fn update(&mut self, host: &mut dyn Host, event: SessionEvent) -> bool {
+        self.log_event(host, event.clone());
+        self.handle(host, event)
+    }
+
+    /// Acts on a message. Messages that lead to others handle those right away, so that a replay
+    /// of the logged messages goes through the same steps.
+    pub fn handle(&mut self, host: &mut dyn Host, event: SessionEvent) -> bool {
+        let takes_over = matches!(
+            event,
+            SessionEvent::NextDeadline
+                | SessionEvent::PreviousDeadline
+                | SessionEvent::ReceivedFromChild(
+                    AuxToMainInterop::AdvanceDeadline
+                        | AuxToMainInterop::RetreatDeadline
+                        | AuxToMainInterop::JumpToCue { .. }
+                        | AuxToMainInterop::JumpToSection(_)
+                )
+        );
+        if takes_over && self.autopilot.take().is_some() {
+            log::info!("The presenter takes over from the autopilot");
+        }
+        match event {
+            SessionEvent::Periodic => {
+                self.periodic(host);
+                if self.autopilot_due() {
+                    log::info!(
+                        "The autopilot moves on from cue {}",
+                        self.deadline_block_idx
+                    );
+                    return self.next_deadline(host);
+                }
+            }
+            SessionEvent::Playing(false) if self.pacer.idle.is_some() => {
+                // The speaker is still talking, so this pause does not stop the speech clock.
+                return false;
+            }
+            SessionEvent::Playing(pl) => {
+                self.is_playing = pl;
+                self.speech_clock.set_paused(!pl, self.clock.now());
+                if pl && self.phase == Phase::NotStarted {
+                    self.start(host);
+                }
+            }
+            SessionEvent::Stalled(stalled) => {
+                self.speech_clock.set_stalled(stalled, self.clock.now());
+            }
+            SessionEvent::VisibilityChanged(hidden) => {
+                self.speech_clock.set_hidden(hidden, self.clock.now());
+            }
+            SessionEvent::RateChange => {
+                if let Some(v) = host.video() {
+                    self.current_rate = v.playback_rate();
+                }
+            }
+            SessionEvent::NextDeadline => return self.next_deadline(host),
+            SessionEvent::PreviousDeadline => {
+                let Some(ref subs) = self.subs else {
+                    return false;
+                };
+                // Going back undoes the advance that led here, be it finishing the last cue or a
+                // jump, and otherwise starts the cue before over.
+                let finished = self.phase == Phase::Finished;
+                let undo = self
+                    .advances
+                    .last()
+                    .filter(|a| a.to == self.deadline_block_idx);
+                let idx = match undo {
+                    Some(advance) => advance.cue,
+                    None if finished => self.deadline_block_idx,
+                    None if self.deadline_block_idx == 0 => return false,
+                    None => self.deadline_block_idx - 1,
+                };
+                let Some(cue) = subs.cues.get(idx) else {
+                    return false;
+                };
+                let (start, end) = (cue.start, cue.end);
+
+                // Undo the advance, carrying on with the time already spent on the cue.
+                let spoken = match undo {
+                    Some(advance) => {
+                        if advance.sampled {
+                            self.block_timing_history.pop();
+                        }
+                        self.advances.pop().map_or(0.0, |a| a.seconds)
+                    }
+                    None => 0.0,
+                };
+                log::info!("Going back to cue {idx}, after {spoken}s on it");
+                if let Some(ref mut run) = self.rehearsal {
+                    if run.cues.last().is_some_and(|t| t.cue == idx) {
+                        run.cues.pop();
+                    }
+                }
+                self.speaking_total -= spoken;
+                self.deadline_block_idx = idx;
+                self.set_phase(host, Phase::at(idx, self.cue_count()));
+                self.speech_clock.restart_from(spoken, self.clock.now());
+                self.update_target_rate();
+
+                // If the video has moved on, put it back where the speaker should be in the cue.
+                let resume =
+                    (start.as_secs_f64() + spoken * self.target_rate).min(end.as_secs_f64());
+                if let Some(v) = host.video() {
+                    if let Some(idle) = self.pacer.idle.take() {
+                        idle.leave(&*v);
+                        v.play();
+                    }
+                    if finished {
+                        v.play();
+                    }
+                    if v.current_time() > resume || v.current_time() < start.as_secs_f64() {
+                        v.set_current_time(resume);
+                        self.current_block = idx;
+                    }
+                }
+            }
+            SessionEvent::ManifestLoaded(Ok(manifest)) => {
+                self.target_rate = manifest.pacing.initial_rate;
+                self.controller_kind = manifest.pacing.controller;
+                self.pacer.controller = self.controller_kind.build(&manifest.pacing);
+                self.pacer.smoother = RateSmoother::new(manifest.pacing.smoothing);
+                self.pacer.rate_bounds = manifest
+                    .pacing
+                    .rate_bounds()
+                    .within(self.browser_rate_bounds);
+                if !manifest.record_session {
+                    self.session = None;
+                }
+                self.manifest = *manifest;
+                host.show_manifest(&self.manifest);
+                host.send_to_child(MainToAuxInterop::KeyBindings(self.manifest.keys.clone()));
+                self.script_track = 0;
+                if self.manifest.autopilot.enabled {
+                    self.start_autopilot(host);
+                }
+                self.load_script(host);
+            }
+            SessionEvent::ManifestLoaded(Err(why)) => {
+                log::error!("{why}");
+                self.session = None;
+                self.load_error = Some(why.into());
+            }
+            SessionEvent::SelectScriptTrack(idx) => {
+                if idx < self.manifest.scripts.len() {
+                    self.script_track = idx;
+                    self.load_script(host);
+                }
+            }
+            SessionEvent::ScriptLoaded {
+                track,
+                name,
+                result,
+            } => {
+                let track = track.map(|t| self.manifest.scripts[t].clone());
+                let format = track.as_ref().and_then(|t| t.format);
+                match result.and_then(|bytes| script::parse_script(&bytes, &name, format)) {
+                    Ok(subs) => {
+                        log::info!("Loaded script with {} cues", subs.cues.len());
+                        self.lint_findings = lint::lint(&subs, &self.manifest.lint);
+                        for finding in &self.lint_findings {
+                            log::warn!("{finding}");
+                        }
+                        host.show_findings(&self.lint_findings);
+                        host.send_to_child(MainToAuxInterop::Sections(subs.sections.clone()));
+                        host.send_to_child(MainToAuxInterop::CueList {
+                            script: self.script_name(),
+                            cues: cue_texts(&subs),
+                        });
+                        self.subs = Some(subs);
+                        self.load_error = None;
+                        self.deadline_block_idx = 0;
+                        self.current_block = 0;
+                        // Timings of the previous script say nothing about this one.
+                        self.block_timing_history.clear();
+                        self.advances.clear();
+                        self.stop_rehearsal(host);
+                        if self.phase != Phase::NotStarted {
+                            self.set_phase(host, Phase::at(0, self.cue_count()));
+                        }
+                        self.duration_model = DurationModel::new(
+                            self.manifest.pacing.duration_model.clone(),
+                            track.as_ref().and_then(|t| t.language.as_deref()),
+                        );
+                        self.update_target_rate();
+                        if let Some(url) = track.as_ref().and_then(|t| t.notes.clone()) {
+                            host.fetch(Fetch::Notes(url));
+                        }
+                        if let Some(url) = track.and_then(|t| t.chapters) {
+                            host.fetch(Fetch::Chapters(url));
+                        }
+                    }
+                    Err(why) => {
+                        log::error!("Could not load script: {why}");
+                        self.load_error = Some(why);
+                    }
+                }
+            }
+            SessionEvent::NotesLoaded(Ok(notes)) => {
+                if let Some(ref mut subs) = self.subs {
+                    subs.apply_notes(&notes);
+                    if let Err(why) = subs.extract_directives() {
+                        log::error!("Could not read pacing directives from the notes: {why}");
+                        self.load_error = Some(why);
+                    }
+                }
+            }
+            SessionEvent::NotesLoaded(Err(why)) => {
+                log::error!("{why}");
+            }
+            SessionEvent::ChaptersLoaded(result) => {
+                let chapters = result.and_then(|bytes| {
+                    script::parse_script(&bytes, "", Some(cues::ScriptFormat::Vtt))
+                });
+                match (chapters, self.subs.as_mut()) {
+                    (Ok(chapters), Some(subs)) => {
+                        subs.apply_chapters(&chapters);
+                        log::info!("Loaded {} sections", subs.sections.len());
+                        let sections = subs.sections.clone();
+                        host.send_to_child(MainToAuxInterop::Sections(sections));
+                    }
+                    (Err(why), _) => log::error!("Could not load chapters: {why}"),
+                    (Ok(_), None) => {}
+                }
+            }
+            SessionEvent::ReceivedFromChild(value) => match value {
+                AuxToMainInterop::AdvanceDeadline => {
+                    return self.handle(host, SessionEvent::NextDeadline)
+                }
+                AuxToMainInterop::RetreatDeadline => {
+                    return self.handle(host, SessionEvent::PreviousDeadline)
+                }
+                AuxToMainInterop::SetIsPlaying(value) => {
+                    let vid = match host.video() {
+                        Some(v) => v,
+                        None => return false,
+                    };
+                    match value {
+                        true => vid.play(),
+                        false => {
+                            vid.pause();
+                            if let Some(idle) = self.pacer.idle.take() {
+                                // The video may already be paused, so no event will tell us.
+                                idle.leave(&*vid);
+                                self.handle(host, SessionEvent::Playing(false));
+                            }
+                        }
+                    }
+                }
+                AuxToMainInterop::Hello => {
+                    host.send_to_child(MainToAuxInterop::KeyBindings(self.manifest.keys.clone()));
+                    if let Some(ref subs) = self.subs {
+                        host.send_to_child(MainToAuxInterop::Sections(subs.sections.clone()));
+                        host.send_to_child(MainToAuxInterop::CueList {
+                            script: self.script_name(),
+                            cues: cue_texts(subs),
+                        });
+                    }
+                    host.send_to_child(MainToAuxInterop::Lifecycle {
+                        phase: self.phase,
+                        summary: (self.phase == Phase::Finished).then(|| self.summary()),
+                    });
+                }
+                AuxToMainInterop::JumpToCue { idx } => self.jump_to_cue(host, idx),
+                AuxToMainInterop::SetRehearsal(true) => {
+                    log::info!("Recording a rehearsal");
+                    self.rehearsal = Some(Run::new(self.clock.now(), self.script_name()));
+                }
+                AuxToMainInterop::SetRehearsal(false) => self.stop_rehearsal(host),
+                AuxToMainInterop::SetAutopilot(true) => self.start_autopilot(host),
+                AuxToMainInterop::SetAutopilot(false) => {
+                    log::info!("The presenter takes over from the autopilot");
+                    self.autopilot = None;
+                }
+                AuxToMainInterop::SetController(kind) => {
+                    log::info!("Switching to the {} pacing controller", kind.name());
+                    self.controller_kind = kind;
+                    self.pacer.controller = kind.build(&self.manifest.pacing);
+                }
+                AuxToMainInterop::JumpToSection(idx) => {
+                    let first_cue = self
+                        .subs
+                        .as_ref()
+                        .and_then(|s| s.sections.get(idx))
+                        .map(|s| s.first_cue);
+                    if let Some(cue) = first_cue {
+                        self.jump_to_cue(host, cue);
+                    }
+                }
+                AuxToMainInterop::ResetRate => {
+                    self.target_rate = self.manifest.pacing.initial_rate;
+                    match host.video() {
+                        Some(v) => self.pacer.rate_problem = v.set_rate(self.target_rate).err(),
+                        None => return false,
+                    };
+                    self.block_timing_history.clear();
+                    self.advances.clear();
+                    self.update_target_rate();
+                }
+            },
+            // The page takes care of these, and the autopilot's rehearsal is only logged for the
+            // replay to hand out again.
+            SessionEvent::NewWindow
+            | SessionEvent::ScriptFileChosen(_)
+            | SessionEvent::AutopilotStarted(_) => return false,
+        }
+        true
+    }
+
+    /// The speaker has finished the deadline cue, or the autopilot decided they would have.
+    pub fn next_deadline(&mut self, host: &mut dyn Host) -> bool {
+        self.advance(host, true)
+    }
+
+    /// Moves the deadline on from the cue it is at.
+    ///
+    /// Unless the advance is `timed` by the speaker, as when a cue advances on its own, the time
+    /// spent on the cue is not used for the estimate.
+    fn advance(&mut self, host: &mut dyn Host, timed: bool) -> bool {
+        let subs = match self.subs {
+            Some(ref s) => s,
+            None => return false,
+        };
+        if self.phase == Phase::Finished {
+            log::info!("The talk is already over");
+            return false;
+        }
+        let Some(deadline_cue) = subs.cues.get(self.deadline_block_idx) else {
+            return false;
+        };
+        let finished_cue = self.deadline_block_idx;
+        let is_last = finished_cue + 1 >= subs.cues.len();
+        if !is_last {
+            self.deadline_block_idx += 1;
+        }
+        let sample = self.speech_clock.restart(self.clock.now());
+        let elapsed = sample.seconds;
+        let true_duration = deadline_cue.duration().as_secs_f64();
+        log::info!("Latest block was read in {elapsed}, but was supposed to take {true_duration}");
+        let ratio = true_duration / elapsed;
+        let plausible = self.manifest.pacing.estimator.is_plausible(ratio);
+        let sampled = timed && sample.clean && plausible;
+        if sampled {
+            self.block_timing_history.push(ratio);
+        } else if timed && !sample.clean {
+            log::warn!(
+                "Not using the timing of this block, since the tab was hidden or it was too short"
+            );
+        } else if timed {
+            log::warn!("Ignoring implausible rate {ratio} for the rate estimate");
+        }
+        self.advances.push(Advance {
+            cue: finished_cue,
+            to: self.deadline_block_idx,
+            seconds: elapsed,
+            sampled,
+        });
+        let video = host.video();
+        if let Some(ref mut run) = self.rehearsal {
+            run.cues.push(CueTiming {
+                cue: finished_cue,
+                cue_id: deadline_cue.id.clone(),
+                wall_time: self.clock.now(),
+                video_time: video.as_ref().map_or(0.0, |v| v.current_time()),
+                rate: video.as_ref().map_or(0.0, |v| v.playback_rate()),
+                speaking_seconds: elapsed,
+                cue_seconds: true_duration,
+            });
+        }
+        self.speaking_total += elapsed;
+        self.update_target_rate();
+        if self.phase == Phase::NotStarted {
+            self.start(host);
+        }
+        if is_last {
+            self.finish(host);
+        } else {
+            self.set_phase(host, Phase::at(self.deadline_block_idx, self.cue_count()));
+        }
+        true
+    }
+
+    /// Recomputes the speaker-rate estimate from the timing history.
+    fn update_target_rate(&mut self) {
+        self.rate_estimate = self
+            .manifest
+            .pacing
+            .estimator
+            .estimate(&self.block_timing_history);
+        let expected = self
+            .duration_model
+            .settings
+            .enabled
+            .then(|| self.subs.as_ref()?.cues.get(self.deadline_block_idx))
+            .flatten()
+            .and_then(|cue| self.duration_model.ratio(cue))
+            .filter(|r| self.manifest.pacing.estimator.is_plausible(*r));
+        self.target_rate = match expected {
+            Some(expected) => self.duration_model.combine(expected, self.rate_estimate),
+            None => self
+                .rate_estimate
+                .map_or(self.manifest.pacing.initial_rate, |e| e.rate),
+        };
+    }
+
+    fn load_script(&mut self, host: &mut dyn Host) {
+        let url = self.manifest.scripts[self.script_track].url.clone();
+        log::info!("Loading script from {url}");
+        self.subs = None;
+        self.load_error = None;
+        host.fetch(Fetch::Script {
+            track: self.script_track,
+            url,
+        });
+    }
+
+    /// Moves the deadline to the given cue and seeks the video to its start.
+    ///
+    /// No timing sample is recorded, since the time spent before the jump says nothing about
+    /// the speaker's pace, but going back returns to where the deadline was.
+    fn jump_to_cue(&mut self, host: &mut dyn Host, idx: usize) {
+        let Some(cue) = self.subs.as_ref().and_then(|s| s.cues.get(idx)) else {
+            return;
+        };
+        log::info!("Jumping to cue {idx}");
+        // The next cue is reached by ordinary pacing, anything else needs a seek.
+        let large = jump_needs_seek(self.current_block, idx);
+        if let Some(v) = host.video() {
+            if large {
+                v.set_current_time(cue.start.as_secs_f64());
+                self.current_block = idx;
+            }
+            if let Some(idle) = self.pacer.idle.take() {
+                if !large {
+                    idle.leave(&*v);
+                }
+                v.play();
+            }
+            if self.phase == Phase::Finished {
+                v.play();
+            }
+        }
+        let seconds = self.speech_clock.restart(self.clock.now()).seconds;
+        self.advances.push(Advance {
+            cue: self.deadline_block_idx,
+            to: idx,
+            seconds,
+            sampled: false,
+        });
+        self.speaking_total += seconds;
+        self.deadline_block_idx = idx;
+        if self.phase != Phase::NotStarted {
+            self.set_phase(host, Phase::at(idx, self.cue_count()));
+        }
+        self.update_target_rate();
+    }
+
+    /// Adds an event to the session log, if there is one.
+    fn log_event(&mut self, host: &dyn Host, event: SessionEvent) {
+        let logged = LoggedEvent {
+            at: self.clock.now(),
+            video: host.video().map(|v| VideoState::of(&*v)),
+            event,
+        };
+        if let Some(ref mut session) = self.session {
+            session.push(logged);
+        }
+    }
+
+    /// Name of the script track in use, as rehearsals are filed under.
+    fn script_name(&self) -> String {
+        self.manifest
+            .scripts
+            .get(self.script_track)
+            .map(|t| t.name().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Lets the autopilot advance the deadline, and starts the video for it.
+    fn start_autopilot(&mut self, host: &mut dyn Host) {
+        log::info!("The autopilot takes over");
+        let run = host.rehearsal_to_follow(&self.manifest.autopilot, &self.script_name());
+        self.log_event(host, SessionEvent::AutopilotStarted(run.clone()));
+        self.autopilot = Some(Autopilot::new(&self.manifest.autopilot, run));
+        if let Some(v) = host.video() {
+            v.play();
+        }
+    }
+
+    /// How long the autopilot gives the deadline cue, if it is on.
+    pub fn autopilot_seconds(&self) -> Option<f64> {
+        let autopilot = self.autopilot.as_ref()?;
+        let cue = self.subs.as_ref()?.cues.get(self.deadline_block_idx)?;
+        Some(autopilot.cue_seconds(self.deadline_block_idx, cue, &self.duration_model))
+    }
+
+    /// Whether the autopilot is on and the deadline cue has had its time.
+    fn autopilot_due(&self) -> bool {
+        matches!(self.phase, Phase::Running | Phase::FinalCue)
+            && self
+                .autopilot_seconds()
+                .is_some_and(|seconds| self.speech_clock.elapsed(self.clock.now()) >= seconds)
+    }
+
+    fn cue_count(&self) -> usize {
+        self.subs.as_ref().map_or(0, |s| s.cues.len())
+    }
+
+    fn set_phase(&mut self, host: &mut dyn Host, phase: Phase) {
+        if self.phase == phase {
+            return;
+        }
+        log::info!("The talk is now {phase:?}");
+        self.phase = phase;
+        host.send_to_child(MainToAuxInterop::Lifecycle {
+            phase,
+            summary: (phase == Phase::Finished).then(|| self.summary()),
+        });
+    }
+
+    fn start(&mut self, host: &mut dyn Host) {
+        self.started_at = self.clock.now();
+        self.set_phase(host, Phase::at(self.deadline_block_idx, self.cue_count()));
+    }
+
+    /// Ends the talk after the last cue: the video stops and the end screen comes up.
+    fn finish(&mut self, host: &mut dyn Host) {
+        self.finished_at = self.clock.now();
+        if let Some(v) = host.video() {
+            if let Some(idle) = self.pacer.idle.take() {
+                idle.leave(&*v);
+            }
+            v.pause();
+        }
+        self.set_phase(host, Phase::Finished);
+        self.stop_rehearsal(host);
+    }
+
+    /// Stops recording, and keeps the run if anything was recorded.
+    fn stop_rehearsal(&mut self, host: &mut dyn Host) {
+        let Some(run) = self.rehearsal.take() else {
+            return;
+        };
+        if run.cues.is_empty() {
+            return;
+        }
+        log::info!("Saving a rehearsal of {} cues", run.cues.len());
+        host.save_run(run);
+    }
+
+    /// Goes back to before the first cue, ready to give the talk again.
+    fn return_to_title(&mut self, host: &mut dyn Host) {
+        log::info!("Returning to the title screen");
+        if let Some(v) = host.video() {
+            v.set_current_time(0.0);
+            // With the autopilot on, the next run starts right away.
+            match self.autopilot {
+                Some(_) => v.play(),
+                None => v.pause(),
+            }
+        }
+        self.deadline_block_idx = 0;
+        self.current_block = 0;
+        self.block_timing_history.clear();
+        self.advances.clear();
+        self.speaking_total = 0.0;
+        self.speech_clock.restart(self.clock.now());
+        self.update_target_rate();
+        self.set_phase(host, Phase::NotStarted);
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary {
+            title: self.manifest.title.clone(),
+            cues: self.cue_count(),
+            talk_seconds: self.finished_at - self.started_at,
+            speaking_seconds: self.speaking_total,
+            rate: estimator::label(self.rate_estimate, self.target_rate),
+        }
+    }
+
+    fn periodic(&mut self, host: &mut dyn Host) {
+        let element = match host.video() {
+            Some(v) => v,
+            None => return,
+        };
+        let subs = match self.subs {
+            Some(ref s) if !s.cues.is_empty() => s,
+            _ => return,
+        };
+        // A cross-fade starts fading out once the cut has been shown, and is done after that.
+        self.crossfade = match self.crossfade {
+            Some(Crossfade::Covering) => Some(Crossfade::Fading {
+                until: self.clock.now() + self.manifest.pacing.catch_up.crossfade_seconds,
+            }),
+            Some(Crossfade::Fading { until }) if self.clock.now() < until => self.crossfade,
+            _ => None,
+        };
+
+        // While idle, the video is elsewhere, but pacing carries on from where it stopped.
+        let mut now = Duration::from_secs_f64(match self.pacer.idle {
+            Some(ref idle) => idle.resume_at,
+            None => element.current_time(),
+        });
+        self.current_time = now;
+        host.send_to_child(MainToAuxInterop::CurrentStatus {
+            time: element.current_time(),
+            rate: element.playback_rate(),
+            playing: !element.paused() || self.pacer.idle.is_some(),
+            controller: self.controller_kind,
+            estimate: self.rate_estimate,
+            target_rate: self.target_rate,
+            rate_problem: self.pacer.rate_problem.clone(),
+            rehearsing: self.rehearsal.is_some(),
+            autopilot: self.autopilot.is_some(),
+        });
+
+        // Send the context
+        let mut prev = vec![];
+        let mut next = vec![];
+        for i in (self.deadline_block_idx as isize) - 2..self.deadline_block_idx as isize {
+            if i < 0 {
+                continue;
+            }
+            prev.push(subs.cues[i as usize].text.clone())
+        }
+        for i in (self.deadline_block_idx as isize + 1)..(self.deadline_block_idx as isize + 5) {
+            if i as usize >= subs.cues.len() {
+                continue;
+            }
+            next.push(subs.cues[i as usize].text.clone())
+        }
+        let ctx = CueContext {
+            current_idx: self.deadline_block_idx as i32,
+            current: subs.cues[self.deadline_block_idx].text.clone(),
+            notes: subs.cues[self.deadline_block_idx].notes.clone(),
+            section: subs.section_of(self.deadline_block_idx),
+            prev,
+            next,
+        };
+
+        host.send_to_child(MainToAuxInterop::CueContext(ctx));
+
+        let sub_list = &subs.cues;
+
+        // Loop over the blocks to find one that the value matches.
+        let idxs = (self.current_block..sub_list.len()).chain(0..self.current_block);
+        for idx in idxs {
+            let cue = &sub_list[idx];
+            // If this cue fits, set this as the current block.
+            if fits(now, cue) {
+                self.current_block = idx;
+                self.current_block_has_passed = false;
+                break;
+            }
+            // Otherwise, if now is after the cue,
+            // then this cue is the last visible one.
+            if cue.end < now {
+                self.current_block = idx.max(self.current_block);
+                self.current_block_has_passed = true;
+            }
+        }
+
+        // Cues that play on their own, like a demo, release the deadline as soon as they start.
+        // The last one has no cue to release it to, so it ends the talk once it has played.
+        let on_screen =
+            (!self.current_block_has_passed).then(|| sub_list[self.current_block].pacing.clone());
+        let played = match self.deadline_block_idx + 1 < sub_list.len() {
+            true => on_screen.is_some(),
+            false => self.pacer.idle.is_some() || now >= sub_list[self.deadline_block_idx].end,
+        };
+        let auto_advance = sub_list[self.deadline_block_idx].pacing.auto_advance
+            && self.current_block == self.deadline_block_idx
+            && self.phase != Phase::Finished
+            && played;
+        if auto_advance {
+            log::info!("Cue {} advances on its own", self.current_block);
+            self.advance(host, false);
+        }
+        let Some(sub_list) = self.subs.as_ref().map(|s| &s.cues) else {
+            return;
+        };
+
+        if self.phase == Phase::Finished {
+            let delay = self.manifest.lifecycle.return_to_title_after;
+            if delay > 0.0 && self.clock.now() - self.finished_at > delay {
+                self.return_to_title(host);
+            }
+            return;
+        }
+
+        // If the speaker has moved on ahead of the video, get there as the deadline cue asks.
+        let catch_up = &self.manifest.pacing.catch_up;
+        let deadline_cue = &sub_list[self.deadline_block_idx];
+        let behind = (deadline_cue.start.as_secs_f64() - now.as_secs_f64()).max(0.0);
+        let strategy = match self.current_block < self.deadline_block_idx && behind > 0.0 {
+            true => Some(catch_up.choose(deadline_cue.pacing.catch_up, behind)),
+            false => None,
+        };
+        match strategy {
+            Some(CatchUpStrategy::Jump) | Some(CatchUpStrategy::Crossfade) => {
+                let fade = strategy == Some(CatchUpStrategy::Crossfade) && host.capture_frame();
+                if fade {
+                    self.crossfade = Some(Crossfade::Covering);
+                }
+                log::info!("Cutting {behind:.1}s ahead to the deadline cue");
+                // The cut ends any idling, and pacing goes on from where it lands.
+                if self.pacer.idle.take().is_some() {
+                    element.play();
+                }
+                element.set_current_time(deadline_cue.start.as_secs_f64());
+                now = Duration::from_secs_f64(element.current_time());
+                self.current_time = now;
+                self.current_block = self.deadline_block_idx;
+                self.current_block_has_passed = false;
+            }
+            _ => {}
+        }
+
+        // Set the playback rate as the pacing controller sees fit.
+        let input = PacingInput {
+            now,
+            cues: sub_list,
+            current_block: self.current_block,
+            deadline_idx: self.deadline_block_idx,
+            target_rate: self.target_rate,
+            wall_time: self.clock.now(),
+            deadline_elapsed: self.speech_clock.elapsed(self.clock.now()),
+        };
+        let max_rate = match strategy {
+            Some(CatchUpStrategy::FastForward) => catch_up.max_rate,
+            _ => f64::INFINITY,
+        };
+        let idle_mode = deadline_cue
+            .pacing
+            .idle
+            .as_ref()
+            .unwrap_or(&self.manifest.pacing.idle);
+        self.pacer.step(
+            &*element,
+            &input,
+            &on_screen.unwrap_or_default(),
+            max_rate,
+            idle_mode,
+        );
+    }
+}
+
+fn cue_texts(script: &Script) -> Vec<String> {
+    script.cues.iter().map(|c| c.text.clone()).collect()
+}
+
+/// Whether a jump from the cue on screen to cue `idx` has to move the video.
+fn jump_needs_seek(current_block: usize, idx: usize) -> bool {
+    idx < current_block || idx > current_block + 1
+}
+
+fn fits(time: Duration, cue: &Cue) -> bool {
+    cue.start <= time && time <= cue.end
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::session::{Replay, SessionLog};
+    use crate::sources::RateWrite;
+
+    const SCRIPT: &str = "WEBVTT
+
+00:00.000 --> 00:08.000
+The first cue
+
+00:08.000 --> 00:16.000
+The second cue
+
+00:16.000 --> 00:24.000
+The last cue
+";
+
+    /// A player on a page of its own, whose video plays at whatever rate it is given.
+    struct Stage {
+        page: Replay,
+        rates: Rc<RefCell<Vec<RateWrite>>>,
+        player: Player,
+    }
+
+    impl Stage {
+        fn new(record: bool) -> Self {
+            let rates = Rc::new(RefCell::new(vec![]));
+            let page = Replay::new(rates.clone());
+            page.video.show(Some(VideoState {
+                time: 0.0,
+                rate: 1.0,
+                paused: true,
+            }));
+            let player = Player::new(page.clock.clone(), RateBounds::default(), record);
+            Self {
+                page,
+                rates,
+                player,
+            }
+        }
+
+        fn send(&mut self, event: SessionEvent) -> bool {
+            self.player.update(&mut self.page, event)
+        }
+
+        /// Loads the manifest and the script, and starts the video.
+        fn start(&mut self, manifest: Manifest, script: &str) {
+            self.send(SessionEvent::ManifestLoaded(Ok(Box::new(manifest))));
+            self.send(SessionEvent::ScriptLoaded {
+                track: Some(0),
+                name: "talk.vtt".to_string(),
+                result: Ok(script.as_bytes().to_vec()),
+            });
+            self.page.video.play();
+            self.send(SessionEvent::Playing(true));
+        }
+
+        /// Lets the video play on for `seconds`, ticking ten times a second.
+        fn play_for(&mut self, seconds: f64) {
+            let (clock, video) = (self.page.clock.clone(), self.page.video.clone());
+            for _ in 0..(seconds * 10.0).round() as usize {
+                clock.set(clock.now() + 0.1);
+                if !video.paused() {
+                    video.set_current_time(video.current_time() + 0.1 * video.playback_rate());
+                }
+                self.send(SessionEvent::Periodic);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_implausible_timings_out_of_the_history() {
+        let mut stage = Stage::new(false);
+        stage.start(Manifest::default(), SCRIPT);
+        // Eight seconds of video read in six, then in one.
+        stage.play_for(6.0);
+        stage.send(SessionEvent::NextDeadline);
+        stage.play_for(1.0);
+        stage.send(SessionEvent::NextDeadline);
+        let history = stage.player.block_timing_history.clone();
+        assert_eq!(history.len(), 1);
+        assert!((history[0] - 8.0 / 6.0).abs() < 1e-9);
+
+        // Going back undoes only the advance that was not sampled.
+        stage.send(SessionEvent::PreviousDeadline);
+        assert_eq!(stage.player.block_timing_history, history);
+        assert_eq!(stage.player.deadline_block_idx, 1);
+    }
+
+    #[test]
+    fn a_last_cue_that_advances_on_its_own_ends_the_talk_once_played() {
+        let mut stage = Stage::new(false);
+        stage.start(
+            Manifest::default(),
+            &SCRIPT.replace(
+                "00:16.000 --> 00:24.000",
+                "00:16.000 --> 00:24.000 advance:auto",
+            ),
+        );
+        for _ in 0..2 {
+            stage.play_for(6.0);
+            stage.send(SessionEvent::NextDeadline);
+        }
+        stage.play_for(2.0);
+        assert_eq!(stage.player.phase, Phase::FinalCue);
+        stage.play_for(20.0);
+        assert_eq!(stage.player.phase, Phase::Finished);
+        assert!(stage.page.video.paused());
+        let advance = stage.player.advances.last().unwrap();
+        assert_eq!((advance.cue, advance.sampled), (2, false));
+    }
+
+    #[test]
+    fn a_new_script_starts_without_the_timings_of_the_old_one() {
+        let mut stage = Stage::new(false);
+        stage.start(Manifest::default(), SCRIPT);
+        stage.send(SessionEvent::ReceivedFromChild(
+            AuxToMainInterop::SetRehearsal(true),
+        ));
+        stage.play_for(6.0);
+        stage.send(SessionEvent::NextDeadline);
+        assert_eq!(stage.player.block_timing_history.len(), 1);
+        assert_eq!(stage.player.rehearsal.as_ref().unwrap().cues.len(), 1);
+
+        stage.send(SessionEvent::ScriptLoaded {
+            track: None,
+            name: "other.vtt".to_string(),
+            result: Ok(SCRIPT.as_bytes().to_vec()),
+        });
+        assert!(stage.player.block_timing_history.is_empty());
+        assert!(stage.player.advances.is_empty());
+        assert!(stage.player.rehearsal.is_none());
+        assert_eq!(stage.player.deadline_block_idx, 0);
+        assert!(!stage.send(SessionEvent::PreviousDeadline));
+    }
+
+    /// Records a talk in which the speaker is faster than the video, then finishes early.
+    fn record() -> SessionLog {
+        let mut live = Stage::new(true);
+        live.start(
+            Manifest {
+                record_session: true,
+                ..Default::default()
+            },
+            SCRIPT,
+        );
+        for _ in 0..2 {
+            live.play_for(6.0);
+            live.send(SessionEvent::NextDeadline);
+        }
+        live.play_for(10.0);
+        SessionLog {
+            rate_bounds: live.player.browser_rate_bounds,
+            events: live.player.session.clone().unwrap(),
+            rates: live.rates.take(),
+        }
+    }
+
+    #[test]
+    fn replays_a_recorded_session_to_the_same_rates() {
+        let session = record();
+        assert!(session.rates.len() > 3, "{:?}", session.rates);
+
+        let mut again = Stage::new(false);
+        let report = again.page.run(&mut again.player, session);
+        assert_eq!(report.first_difference(), None, "{}", report.describe());
+        assert_eq!(again.player.deadline_block_idx, 2);
+        assert_eq!(again.player.pacer.rate_problem, None);
+    }
+
+    #[test]
+    fn a_replay_is_refused_the_rates_the_recording_was() {
+        let mut session = record();
+        let why = "The browser does not play at that rate".to_string();
+        session.rates.last_mut().unwrap().result = Err(why.clone());
+
+        let mut again = Stage::new(false);
+        let report = again.page.run(&mut again.player, session);
+        assert_eq!(report.first_difference(), None, "{}", report.describe());
+        assert_eq!(again.player.pacer.rate_problem, Some(why));
+    }
+
+    #[test]
+    fn lists_the_text_of_every_cue() {
+        let script = Script {
+            cues: ["Hello", "<b>World</b>"]
+                .map(|text| Cue {
+                    text: text.to_string(),
+                    ..Default::default()
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(cue_texts(&script), ["Hello", "<b>World</b>"]);
+    }
+
+    #[test]
+    fn seeks_unless_jumping_to_the_cue_on_screen_or_the_next() {
+        assert!(!jump_needs_seek(3, 3));
+        assert!(!jump_needs_seek(3, 4));
+        assert!(jump_needs_seek(3, 5));
+        assert!(jump_needs_seek(3, 2));
+        assert!(jump_needs_seek(3, 0));
+    }
+}
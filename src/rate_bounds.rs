@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlVideoElement;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Sets the playback rate, returning the browser's complaint if it refused.
+    fn try_set_playback_rate(el: &HtmlVideoElement, rate: f64) -> Option<String>;
+}
+
+/// The range of playback rates the video may be given.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RateBounds {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for RateBounds {
+    /// The range every current browser accepts for a muted video.
+    fn default() -> Self {
+        Self {
+            min: 0.0625,
+            max: 16.0,
+        }
+    }
+}
+
+/// Rates tried when probing, from the most extreme inwards.
+const SLOW_CANDIDATES: &[f64] = &[0.0625, 0.1, 0.25, 0.5];
+const FAST_CANDIDATES: &[f64] = &[16.0, 8.0, 4.0, 2.0];
+
+impl RateBounds {
+    /// The range allowed by both `self` and `other`.
+    pub fn intersect(self, other: RateBounds) -> RateBounds {
+        RateBounds {
+            min: self.min.max(other.min),
+            max: self.max.min(other.max),
+        }
+    }
+
+    /// The part of `self` that `browser` supports, or all of `browser` if they do not overlap.
+    pub fn within(self, browser: RateBounds) -> RateBounds {
+        let bounds = self.intersect(browser);
+        if bounds.min > bounds.max {
+            log::warn!(
+                "The manifest's playback rates are outside the {} to {} this browser supports",
+                browser.min,
+                browser.max
+            );
+            return browser;
+        }
+        bounds
+    }
+
+    /// Finds out which rates this browser accepts by trying them on a video element that is
+    /// never shown.
+    pub fn probe() -> RateBounds {
+        let el: HtmlVideoElement = match gloo::utils::document().create_element("video") {
+            Ok(el) => el.unchecked_into(),
+            Err(_) => return RateBounds::default(),
+        };
+        el.set_muted(true);
+        let accepts = |rate: f64| set_rate(&el, rate).is_ok();
+        let detected = RateBounds {
+            min: SLOW_CANDIDATES
+                .iter()
+                .copied()
+                .find(|r| accepts(*r))
+                .unwrap_or(1.0),
+            max: FAST_CANDIDATES
+                .iter()
+                .copied()
+                .find(|r| accepts(*r))
+                .unwrap_or(1.0),
+        };
+        log::info!(
+            "Browser accepts playback rates from {} to {}",
+            detected.min,
+            detected.max
+        );
+        detected
+    }
+}
+
+/// Sets the playback rate, and checks that the browser did not quietly pick another one.
+pub fn set_rate(el: &HtmlVideoElement, rate: f64) -> Result<(), String> {
+    if let Some(why) = try_set_playback_rate(el, rate) {
+        return Err(format!("Browser refused playback rate {rate:.3}: {why}"));
+    }
+    let actual = el.playback_rate();
+    if (actual - rate).abs() > 1e-3 {
+        return Err(format!(
+            "Browser plays at {actual:.3} instead of the requested rate {rate:.3}"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BROWSER: RateBounds = RateBounds {
+        min: 0.25,
+        max: 8.0,
+    };
+
+    #[test]
+    fn intersects_with_the_probed_range() {
+        let manifest = RateBounds {
+            min: 0.0625,
+            max: 4.0,
+        };
+        assert_eq!(
+            manifest.intersect(BROWSER),
+            RateBounds {
+                min: 0.25,
+                max: 4.0
+            }
+        );
+        assert_eq!(manifest.within(BROWSER), manifest.intersect(BROWSER));
+    }
+
+    #[test]
+    fn falls_back_to_the_browser_range_when_disjoint() {
+        let manifest = RateBounds {
+            min: 10.0,
+            max: 12.0,
+        };
+        assert_eq!(manifest.within(BROWSER), BROWSER);
+    }
+
+    #[test]
+    fn defaults_to_what_every_browser_accepts() {
+        let bounds = RateBounds::default();
+        assert_eq!(bounds.min, SLOW_CANDIDATES[0]);
+        assert_eq!(bounds.max, FAST_CANDIDATES[0]);
+        assert_eq!(bounds.within(bounds), bounds);
+    }
+}
@@ -0,0 +1,221 @@
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+use web_sys::js_sys;
+use yew::prelude::*;
+
+/// Where the recorded runs are kept in `localStorage`, shared by both windows.
+const STORAGE_KEY: &str = "video-presentation.rehearsals";
+
+/// One advance of the deadline during a rehearsal.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CueTiming {
+    /// Index of the cue that was finished.
+    pub cue: usize,
+    pub cue_id: String,
+    /// Wall-clock time of the advance, in seconds since the epoch.
+    pub wall_time: f64,
+    /// Position of the video at the advance.
+    pub video_time: f64,
+    /// Playback rate at the advance.
+    pub rate: f64,
+    /// Active speaking time spent on the cue.
+    pub speaking_seconds: f64,
+    /// How long the cue lasts in the video.
+    pub cue_seconds: f64,
+}
+
+impl CueTiming {
+    /// Speaking time relative to the cue's length in the video: above 1 is slower than the video.
+    pub fn drag(&self) -> f64 {
+        self.speaking_seconds / self.cue_seconds.max(1e-3)
+    }
+}
+
+/// One run through the script.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Run {
+    /// Wall-clock time the recording started, in seconds since the epoch.
+    pub started_at: f64,
+    /// Name of the script that was rehearsed.
+    pub script: String,
+    pub cues: Vec<CueTiming>,
+}
+
+impl Run {
+    pub fn new(started_at: f64, script: String) -> Self {
+        Self {
+            started_at,
+            script,
+            cues: vec![],
+        }
+    }
+
+    /// The date and time of the run, for display.
+    pub fn label(&self) -> String {
+        js_sys::Date::new(&(self.started_at * 1000.0).into())
+            .to_locale_string("default", &Default::default())
+            .into()
+    }
+
+    /// The latest timing of the given cue, if it was reached.
+    pub fn timing(&self, cue: usize) -> Option<&CueTiming> {
+        self.cues.iter().rev().find(|t| t.cue == cue)
+    }
+}
+
+/// All runs recorded so far, oldest first.
+pub fn load_runs() -> Vec<Run> {
+    LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+}
+
+/// The runs of the script with the given name, oldest first.
+pub fn runs_of(runs: Vec<Run>, script: &str) -> Vec<Run> {
+    runs.into_iter().filter(|r| r.script == script).collect()
+}
+
+/// Adds a run to the recorded ones.
+pub fn save_run(run: Run) -> Result<(), String> {
+    let mut runs = load_runs();
+    runs.push(run);
+    LocalStorage::set(STORAGE_KEY, runs).map_err(|e| format!("Could not save the rehearsal: {e}"))
+}
+
+pub fn clear_runs() {
+    LocalStorage::delete(STORAGE_KEY);
+}
+
+/// Offers all recorded runs for download as a JSON file.
+pub fn export_runs() -> Result<(), String> {
+    crate::common::download_json(&load_runs(), "rehearsals.json")
+}
+
+/// Speaking more than this much slower or faster than the video counts as dragging or rushing.
+const TOLERANCE: f64 = 0.15;
+
+fn pace_class(drag: f64) -> &'static str {
+    if drag > 1.0 + TOLERANCE {
+        "text-danger"
+    } else if drag < 1.0 - TOLERANCE {
+        "text-primary"
+    } else {
+        ""
+    }
+}
+
+/// A table of the speaking time of every cue in each run of one script, marking where the speaker
+/// drags (red) or rushes (blue), with the average over all runs in the last column.
+pub fn comparison(runs: &[Run], cue_texts: &[String]) -> Html {
+    let cue_count = runs
+        .iter()
+        .flat_map(|r| r.cues.iter().map(|t| t.cue + 1))
+        .chain([cue_texts.len()])
+        .max()
+        .unwrap_or(0);
+    let header = runs
+        .iter()
+        .map(|r| html!(<th scope="col">{r.label()}</th>))
+        .collect::<Html>();
+    let rows = (0..cue_count)
+        .map(|cue| {
+            let timings = runs.iter().map(|r| r.timing(cue)).collect::<Vec<_>>();
+            let cells = timings
+                .iter()
+                .map(|t| match t {
+                    Some(t) => html!(
+                        <td class={pace_class(t.drag())}>{format!("{:.1}s", t.speaking_seconds)}</td>
+                    ),
+                    None => html!(<td>{"–"}</td>),
+                })
+                .collect::<Html>();
+            let drags = timings.iter().flatten().map(|t| t.drag()).collect::<Vec<_>>();
+            let average = match drags.len() {
+                0 => html!(<td>{"–"}</td>),
+                n => {
+                    let mean = drags.iter().sum::<f64>() / n as f64;
+                    html!(<td class={pace_class(mean)}>{format!("{:.0}%", mean * 100.0)}</td>)
+                }
+            };
+            let text = cue_texts.get(cue).map(String::as_str).unwrap_or_default();
+            html!(
+                <tr>
+                    <th scope="row">{cue}</th>
+                    <td>{crate::markup::plain_text(text)}</td>
+                    {cells}
+                    {average}
+                </tr>
+            )
+        })
+        .collect::<Html>();
+    html!(
+        <table class="table table-sm">
+            <thead>
+                <tr>
+                    <th scope="col">{"#"}</th>
+                    <th scope="col">{"Cue"}</th>
+                    {header}
+                    <th scope="col">{"Average"}</th>
+                </tr>
+            </thead>
+            <tbody>{rows}</tbody>
+        </table>
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(cue: usize, speaking_seconds: f64, cue_seconds: f64) -> CueTiming {
+        CueTiming {
+            cue,
+            cue_id: String::new(),
+            wall_time: 0.0,
+            video_time: 0.0,
+            rate: 1.0,
+            speaking_seconds,
+            cue_seconds,
+        }
+    }
+
+    #[test]
+    fn drag_compares_speaking_time_with_the_video() {
+        assert_eq!(timing(0, 6.0, 4.0).drag(), 1.5);
+        assert_eq!(timing(0, 2.0, 4.0).drag(), 0.5);
+        assert!(timing(0, 2.0, 0.0).drag().is_finite());
+    }
+
+    #[test]
+    fn marks_dragging_and_rushing_beyond_the_tolerance() {
+        assert_eq!(pace_class(1.0), "");
+        assert_eq!(pace_class(1.15), "");
+        assert_eq!(pace_class(0.85), "");
+        assert_eq!(pace_class(1.2), "text-danger");
+        assert_eq!(pace_class(0.8), "text-primary");
+    }
+
+    #[test]
+    fn compares_only_runs_of_the_same_script() {
+        let runs = vec![
+            Run::new(1.0, "talk.vtt".to_string()),
+            Run::new(2.0, "other.vtt".to_string()),
+            Run::new(3.0, "talk.vtt".to_string()),
+        ];
+        let started = |runs: Vec<Run>| runs.iter().map(|r| r.started_at).collect::<Vec<_>>();
+        assert_eq!(started(runs_of(runs.clone(), "talk.vtt")), [1.0, 3.0]);
+        assert_eq!(started(runs_of(runs.clone(), "other.vtt")), [2.0]);
+        assert!(runs_of(runs, "notes.vtt").is_empty());
+    }
+
+    #[test]
+    fn uses_the_latest_timing_of_a_cue() {
+        let mut run = Run::new(0.0, "talk.vtt".to_string());
+        run.cues = vec![
+            timing(0, 3.0, 4.0),
+            timing(1, 5.0, 4.0),
+            timing(0, 4.0, 4.0),
+        ];
+        assert_eq!(run.timing(0).map(|t| t.speaking_seconds), Some(4.0));
+        assert_eq!(run.timing(1).map(|t| t.speaking_seconds), Some(5.0));
+        assert_eq!(run.timing(2), None);
+    }
+}
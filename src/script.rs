@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cues::{Script, ScriptFormat};
+
+/// Where the script is fetched from if the page URL does not say otherwise.
+pub const DEFAULT_SCRIPT_URL: &str = "/media/subs-verbose-de.vtt";
+
+/// Why a script could not be loaded, and where in the file the problem is.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    /// 1-based line number of the offending line, if the error is tied to one.
+    pub line: Option<usize>,
+    /// Full text of the block containing the error.
+    pub block: Option<String>,
+    pub reason: String,
+}
+
+impl ScriptError {
+    pub(crate) fn at(line: usize, block: &[&str], reason: String) -> Self {
+        Self {
+            line: Some(line),
+            block: Some(block.join("\n")),
+            reason,
+        }
+    }
+}
+
+impl From<String> for ScriptError {
+    fn from(reason: String) -> Self {
+        Self {
+            line: None,
+            block: None,
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+/// Downloads the raw script bytes from the given URL.
+pub async fn fetch_script(url: &str) -> Result<Vec<u8>, ScriptError> {
+    Ok(crate::common::fetch_bytes(url, "script").await?)
+}
+
+/// Decodes the script bytes into text with `\n` line endings.
+///
+/// UTF-8 and UTF-16 (either byte order) are accepted, with or without a byte order mark.
+pub fn decode_script(bytes: &[u8]) -> Result<String, ScriptError> {
+    let text = match bytes {
+        [0xef, 0xbb, 0xbf, rest @ ..] => decode_utf8(rest)?,
+        [0xff, 0xfe, rest @ ..] => decode_utf16(rest, u16::from_le_bytes)?,
+        [0xfe, 0xff, rest @ ..] => decode_utf16(rest, u16::from_be_bytes)?,
+        // Without a BOM, an ASCII first character gives the byte order away.
+        [c, 0, ..] if c.is_ascii() && *c != 0 => decode_utf16(bytes, u16::from_le_bytes)?,
+        [0, c, ..] if c.is_ascii() && *c != 0 => decode_utf16(bytes, u16::from_be_bytes)?,
+        _ => decode_utf8(bytes)?,
+    };
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+    Ok(text.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+fn decode_utf8(bytes: &[u8]) -> Result<String, ScriptError> {
+    String::from_utf8(bytes.to_vec()).map_err(|e| {
+        let valid = &bytes[..e.utf8_error().valid_up_to()];
+        let line = valid.iter().filter(|b| **b == b'\n').count() + 1;
+        ScriptError {
+            line: Some(line),
+            block: None,
+            reason: "the file is not valid UTF-8 or UTF-16 text".to_string(),
+        }
+    })
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String, ScriptError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err("the file looks like UTF-16, but has an odd number of bytes"
+            .to_string()
+            .into());
+    }
+    let units = bytes
+        .chunks_exact(2)
+        .map(|c| unit([c[0], c[1]]))
+        .collect::<Vec<_>>();
+    String::from_utf16(&units).map_err(|_| {
+        "the file looks like UTF-16, but contains invalid characters"
+            .to_string()
+            .into()
+    })
+}
+
+/// Decodes and parses the script bytes.
+///
+/// The format is taken from `format` if given, then from the extension of `name`, and is
+/// otherwise guessed from the contents.
+pub fn parse_script(
+    bytes: &[u8],
+    name: &str,
+    format: Option<ScriptFormat>,
+) -> Result<Script, ScriptError> {
+    let text = decode_script(bytes)?;
+    if text.trim().is_empty() {
+        return Err("the script file is empty".to_string().into());
+    }
+    let format = format
+        .or_else(|| ScriptFormat::from_name(name))
+        .unwrap_or_else(|| ScriptFormat::sniff(&text));
+    let mut script = format.source().parse(&text)?;
+    script.extract_inline_notes();
+    script.extract_directives()?;
+    Ok(script)
+}
+
+/// Downloads a notes file: a JSON object mapping cue identifiers to presenter notes.
+pub async fn fetch_notes(url: &str) -> Result<HashMap<String, String>, String> {
+    crate::common::fetch_json(url, "notes").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, unit: fn(u16) -> [u8; 2]) -> Vec<u8> {
+        text.encode_utf16().flat_map(unit).collect()
+    }
+
+    #[test]
+    fn decodes_utf8_with_and_without_bom() {
+        assert_eq!(decode_script(b"WEBVTT\n").unwrap(), "WEBVTT\n");
+        assert_eq!(decode_script(b"\xef\xbb\xbfWEBVTT\n").unwrap(), "WEBVTT\n");
+    }
+
+    #[test]
+    fn decodes_utf16_in_either_byte_order() {
+        let text = "WEBVTT\nÄrger\n";
+        let le = [vec![0xff, 0xfe], utf16(text, u16::to_le_bytes)].concat();
+        let be = [vec![0xfe, 0xff], utf16(text, u16::to_be_bytes)].concat();
+        assert_eq!(decode_script(&le).unwrap(), text);
+        assert_eq!(decode_script(&be).unwrap(), text);
+        // Without a BOM, the ASCII `W` gives the byte order away.
+        assert_eq!(decode_script(&utf16(text, u16::to_le_bytes)).unwrap(), text);
+        assert_eq!(decode_script(&utf16(text, u16::to_be_bytes)).unwrap(), text);
+    }
+
+    #[test]
+    fn rejects_utf16_with_an_odd_number_of_bytes() {
+        let err = decode_script(&[0xff, 0xfe, b'W', 0, b'E']).unwrap_err();
+        assert!(err.reason.contains("odd number of bytes"), "{err}");
+    }
+
+    #[test]
+    fn reports_the_line_of_invalid_utf8() {
+        let err = decode_script(b"WEBVTT\n\nab\xff\n").unwrap_err();
+        assert_eq!(err.line, Some(3));
+    }
+
+    #[test]
+    fn normalises_line_endings() {
+        assert_eq!(decode_script(b"a\r\nb\rc\n").unwrap(), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn parses_crlf_scripts() {
+        let text = "WEBVTT\r\n\r\n00:01.000 --> 00:02.000\r\nHello\r\n";
+        let script = parse_script(text.as_bytes(), "talk.vtt", None).unwrap();
+        assert_eq!(script.cues.len(), 1);
+        assert_eq!(script.cues[0].text, "Hello");
+    }
+
+    #[test]
+    fn rejects_empty_scripts() {
+        let err = parse_script(b"  \n\n", "talk.vtt", None).unwrap_err();
+        assert_eq!(err.reason, "the script file is empty");
+    }
+}
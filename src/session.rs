@@ -0,0 +1,314 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::autopilot::AutopilotSettings;
+use crate::aux::{AuxToMainInterop, MainToAuxInterop};
+use crate::lint::Finding;
+use crate::manifest::Manifest;
+use crate::player::{Fetch, Host, Player};
+use crate::rate_bounds::RateBounds;
+use crate::rehearsal::Run;
+use crate::script::ScriptError;
+use crate::sources::{RateWrite, ReplayClock, ReplayVideo, VideoSource, VideoState};
+use crate::Msg;
+
+/// A message that reached the main window, in a form that can be saved and replayed.
+///
+/// Only what the message carried is kept: the video and the clock are recorded alongside it in
+/// [`LoggedEvent`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionEvent {
+    Periodic,
+    Playing(bool),
+    Stalled(bool),
+    VisibilityChanged(bool),
+    RateChange,
+    NextDeadline,
+    PreviousDeadline,
+    /// The aux window was opened; a replay does not open it again.
+    NewWindow,
+    ReceivedFromChild(AuxToMainInterop),
+    ManifestLoaded(Result<Box<Manifest>, String>),
+    SelectScriptTrack(usize),
+    ScriptLoaded {
+        track: Option<usize>,
+        name: String,
+        result: Result<Vec<u8>, ScriptError>,
+    },
+    NotesLoaded(Result<HashMap<String, String>, String>),
+    ChaptersLoaded(Result<Vec<u8>, ScriptError>),
+    /// A local script file was picked; what was read from it follows as `script-loaded`.
+    ScriptFileChosen(String),
+    /// The autopilot was turned on, following this rehearsal. It is logged because the saved
+    /// rehearsals may be different by the time of the replay.
+    AutopilotStarted(Option<Run>),
+}
+
+impl SessionEvent {
+    /// The event for a message, unless the message is about recording or replaying itself.
+    pub(crate) fn of(msg: &Msg) -> Option<Self> {
+        Some(match msg {
+            Msg::Periodic => SessionEvent::Periodic,
+            Msg::Playing(playing) => SessionEvent::Playing(*playing),
+            Msg::Stalled(stalled) => SessionEvent::Stalled(*stalled),
+            Msg::VisibilityChanged(hidden) => SessionEvent::VisibilityChanged(*hidden),
+            Msg::RateChange => SessionEvent::RateChange,
+            Msg::NextDeadline => SessionEvent::NextDeadline,
+            Msg::PreviousDeadline => SessionEvent::PreviousDeadline,
+            Msg::NewWindow(_) => SessionEvent::NewWindow,
+            Msg::ReceivedFromChild(value) => SessionEvent::ReceivedFromChild(value.clone()),
+            Msg::ManifestLoaded(result) => SessionEvent::ManifestLoaded(result.clone()),
+            Msg::SelectScriptTrack(idx) => SessionEvent::SelectScriptTrack(*idx),
+            Msg::ScriptLoaded {
+                track,
+                name,
+                result,
+            } => SessionEvent::ScriptLoaded {
+                track: *track,
+                name: name.clone(),
+                result: result.clone(),
+            },
+            Msg::NotesLoaded(result) => SessionEvent::NotesLoaded(result.clone()),
+            Msg::ChaptersLoaded(result) => SessionEvent::ChaptersLoaded(result.clone()),
+            Msg::ScriptFileChosen(file) => SessionEvent::ScriptFileChosen(file.name()),
+            Msg::ExportSession | Msg::ReplayLoaded(_) => return None,
+        })
+    }
+}
+
+/// One message, with the time it arrived and what the video was doing then.
+///
+/// It is saved as an `[at, video, event]` array, which keeps the many `periodic` ticks short.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(
+    from = "(f64, Option<VideoState>, SessionEvent)",
+    into = "(f64, Option<VideoState>, SessionEvent)"
+)]
+pub struct LoggedEvent {
+    pub at: f64,
+    pub video: Option<VideoState>,
+    pub event: SessionEvent,
+}
+
+impl From<(f64, Option<VideoState>, SessionEvent)> for LoggedEvent {
+    fn from((at, video, event): (f64, Option<VideoState>, SessionEvent)) -> Self {
+        Self { at, video, event }
+    }
+}
+
+impl From<LoggedEvent> for (f64, Option<VideoState>, SessionEvent) {
+    fn from(logged: LoggedEvent) -> Self {
+        (logged.at, logged.video, logged.event)
+    }
+}
+
+/// Everything needed to replay a session, and the playback rates it should come out with.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SessionLog {
+    /// The rates the browser accepted, which a replay must not probe again.
+    pub rate_bounds: RateBounds,
+    pub events: Vec<LoggedEvent>,
+    /// Every playback rate the video was given, in order, with how it took it.
+    pub rates: Vec<RateWrite>,
+}
+
+/// Downloads a session log saved from an earlier session.
+pub async fn fetch_log(url: &str) -> Result<SessionLog, String> {
+    crate::common::fetch_json(url, "session log").await
+}
+
+/// The clock and video a replay runs against, and how it went.
+pub struct Replay {
+    pub clock: Rc<ReplayClock>,
+    pub video: Rc<ReplayVideo>,
+    /// Where the video notes down the playback rates it is given.
+    rates: Rc<RefCell<Vec<RateWrite>>>,
+    /// The rehearsals the autopilot followed in the recording, for it to follow again.
+    pub autopilot_runs: VecDeque<Option<Run>>,
+    pub report: Option<Result<ReplayReport, String>>,
+}
+
+impl Replay {
+    /// A replay whose video notes its playback rates down in `rates`.
+    pub fn new(rates: Rc<RefCell<Vec<RateWrite>>>) -> Self {
+        Self {
+            clock: Rc::default(),
+            video: Rc::new(ReplayVideo::new(rates.clone())),
+            rates,
+            autopilot_runs: VecDeque::new(),
+            report: None,
+        }
+    }
+
+    /// Feeds a recorded session through the player, against the recorded clock and video.
+    pub fn run(&mut self, player: &mut Player, session: SessionLog) -> ReplayReport {
+        self.autopilot_runs = (session.events.iter())
+            .filter_map(|logged| match logged.event {
+                SessionEvent::AutopilotStarted(ref run) => Some(run.clone()),
+                _ => None,
+            })
+            .collect();
+        self.video.answer_as(&session.rates);
+        player.browser_rate_bounds = session.rate_bounds;
+        player.pacer.rate_bounds = session.rate_bounds;
+        let events = session.events.len();
+        for logged in session.events {
+            self.clock.set(logged.at);
+            self.video.show(logged.video);
+            player.handle(self, logged.event);
+        }
+        ReplayReport {
+            events,
+            recorded: session.rates,
+            replayed: self.rates.borrow().clone(),
+        }
+    }
+}
+
+/// Nothing a replay does reaches the page, the aux window or the saved rehearsals.
+impl Host for Replay {
+    fn video(&self) -> Option<Rc<dyn VideoSource>> {
+        self.video.is_present().then(|| self.video.clone() as _)
+    }
+
+    fn send_to_child(&self, _: MainToAuxInterop) {}
+
+    /// What was fetched is in the log, as the event that brought it.
+    fn fetch(&self, _: Fetch) {}
+
+    fn show_manifest(&mut self, _: &Manifest) {}
+
+    fn show_findings(&self, _: &[Finding]) {}
+
+    fn capture_frame(&self) -> bool {
+        false
+    }
+
+    /// The rehearsal the autopilot followed in the recording, in turn.
+    fn rehearsal_to_follow(&mut self, _: &AutopilotSettings, _: &str) -> Option<Run> {
+        self.autopilot_runs.pop_front().flatten()
+    }
+
+    /// The run was saved while it was recorded.
+    fn save_run(&self, _: Run) {}
+}
+
+/// The playback rates of the recorded session next to those of its replay.
+#[derive(Serialize, Debug, Clone)]
+pub struct ReplayReport {
+    pub events: usize,
+    pub recorded: Vec<RateWrite>,
+    pub replayed: Vec<RateWrite>,
+}
+
+impl ReplayReport {
+    /// The index of the first rate that came out differently, if any did.
+    pub fn first_difference(&self) -> Option<usize> {
+        let common = self.recorded.len().min(self.replayed.len());
+        (self.recorded.iter().zip(&self.replayed))
+            .position(|(a, b)| a != b)
+            .or_else(|| (self.recorded.len() != self.replayed.len()).then_some(common))
+    }
+
+    pub fn describe(&self) -> String {
+        let rate = |rates: &[RateWrite], i: usize| match rates.get(i) {
+            None => "nothing".to_string(),
+            Some(RateWrite {
+                rate,
+                result: Ok(()),
+            }) => format!("{rate:.4}"),
+            Some(RateWrite {
+                rate,
+                result: Err(why),
+            }) => format!("{rate:.4} (refused: {why})"),
+        };
+        match self.first_difference() {
+            None => format!(
+                "Replayed {} events: all {} playback rates match the recording",
+                self.events,
+                self.recorded.len()
+            ),
+            Some(i) => format!(
+                "Replayed {} events: playback rate {i} was {} in the recording but {} in the replay",
+                self.events,
+                rate(&self.recorded, i),
+                rate(&self.replayed, i)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taken(rates: &[f64]) -> Vec<RateWrite> {
+        (rates.iter())
+            .map(|&rate| RateWrite {
+                rate,
+                result: Ok(()),
+            })
+            .collect()
+    }
+
+    fn report(recorded: &[f64], replayed: &[f64]) -> ReplayReport {
+        ReplayReport {
+            events: 10,
+            recorded: taken(recorded),
+            replayed: taken(replayed),
+        }
+    }
+
+    #[test]
+    fn matching_rates_have_no_difference() {
+        let same = report(&[1.0, 1.5, 0.0], &[1.0, 1.5, 0.0]);
+        assert_eq!(same.first_difference(), None);
+        assert_eq!(
+            same.describe(),
+            "Replayed 10 events: all 3 playback rates match the recording"
+        );
+    }
+
+    #[test]
+    fn finds_the_first_rate_that_differs() {
+        let differs = report(&[1.0, 1.5, 0.0], &[1.0, 1.25, 0.5]);
+        assert_eq!(differs.first_difference(), Some(1));
+        assert_eq!(
+            differs.describe(),
+            "Replayed 10 events: playback rate 1 was 1.5000 in the recording but 1.2500 in the replay"
+        );
+    }
+
+    #[test]
+    fn a_missing_rate_is_a_difference() {
+        let short = report(&[1.0, 1.5], &[1.0]);
+        assert_eq!(short.first_difference(), Some(1));
+        assert!(short.describe().ends_with("but nothing in the replay"));
+        assert_eq!(report(&[1.0], &[1.0, 2.0]).first_difference(), Some(1));
+    }
+
+    #[test]
+    fn a_refused_rate_is_a_difference() {
+        let mut refused = report(&[1.0, 20.0], &[1.0, 20.0]);
+        refused.recorded[1].result = Err("too fast".to_string());
+        assert_eq!(refused.first_difference(), Some(1));
+        assert_eq!(
+            refused.describe(),
+            "Replayed 10 events: playback rate 1 was 20.0000 (refused: too fast) in the recording but 20.0000 in the replay"
+        );
+    }
+
+    #[test]
+    fn logs_only_what_is_part_of_the_session() {
+        assert!(SessionEvent::of(&Msg::ExportSession).is_none());
+        assert!(matches!(
+            SessionEvent::of(&Msg::Playing(true)),
+            Some(SessionEvent::Playing(true))
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+/// Limits on how quickly the playback rate may change, whatever the controller asks for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct SmoothingSettings {
+    /// Largest change of the rate per second; 0 lets it jump.
+    pub max_change_per_second: f64,
+    /// The rate only starts moving once the requested rate is further than this from it, and
+    /// then follows until it gets there.
+    pub hysteresis: f64,
+    /// Changes smaller than this are not written to the video at all.
+    pub min_write: f64,
+}
+
+impl Default for SmoothingSettings {
+    fn default() -> Self {
+        Self {
+            max_change_per_second: 4.0,
+            hysteresis: 0.0,
+            min_write: 0.01,
+        }
+    }
+}
+
+/// Runs the controller's output through [`SmoothingSettings`].
+///
+/// Time is measured on the wall clock, so it makes no difference how often the rate is checked.
+#[derive(Debug, Clone, Default)]
+pub struct RateSmoother {
+    pub settings: SmoothingSettings,
+    /// The last rate given out, and when.
+    output: Option<(f64, f64)>,
+    /// Whether the output is on its way to the requested rate, past the hysteresis band.
+    following: bool,
+}
+
+impl RateSmoother {
+    pub fn new(settings: SmoothingSettings) -> Self {
+        Self {
+            settings,
+            output: None,
+            following: false,
+        }
+    }
+
+    /// Returns the rate to play at now, moving from the last one towards `requested`.
+    pub fn smooth(&mut self, requested: f64, now: f64) -> f64 {
+        let Some((last, at)) = self.output else {
+            self.output = Some((requested, now));
+            return requested;
+        };
+
+        let diff = requested - last;
+        if diff.abs() > self.settings.hysteresis {
+            self.following = true;
+        }
+        let max_step = match self.settings.max_change_per_second {
+            m if m > 0.0 => m * (now - at).max(0.0),
+            _ => f64::INFINITY,
+        };
+        let rate = if !self.following {
+            last
+        } else if diff.abs() <= max_step {
+            requested
+        } else {
+            last + max_step.copysign(diff)
+        };
+        if rate == requested {
+            self.following = false;
+        }
+        self.output = Some((rate, now));
+        rate
+    }
+
+    /// Jumps straight to `rate`, for rates that must be met exactly.
+    pub fn reset_to(&mut self, rate: f64, now: f64) -> f64 {
+        self.output = Some((rate, now));
+        self.following = false;
+        rate
+    }
+
+    /// Whether a rate this close to the one the video has is worth writing.
+    pub fn worth_writing(&self, rate: f64, current: f64) -> bool {
+        (rate - current).abs() >= self.settings.min_write
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smoother(max_change_per_second: f64, hysteresis: f64) -> RateSmoother {
+        RateSmoother::new(SmoothingSettings {
+            max_change_per_second,
+            hysteresis,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn starts_at_the_first_requested_rate() {
+        assert_eq!(smoother(1.0, 0.0).smooth(2.0, 0.0), 2.0);
+    }
+
+    #[test]
+    fn limits_the_change_per_second() {
+        let mut s = smoother(1.0, 0.0);
+        s.smooth(1.0, 0.0);
+        assert_eq!(s.smooth(3.0, 0.5), 1.5);
+        assert_eq!(s.smooth(3.0, 1.5), 2.5);
+        assert_eq!(s.smooth(3.0, 2.5), 3.0);
+        assert_eq!(s.smooth(0.0, 3.0), 2.5);
+        // Time running backwards does not move the rate.
+        assert_eq!(s.smooth(0.0, 2.0), 2.5);
+    }
+
+    #[test]
+    fn jumps_without_a_limit() {
+        let mut s = smoother(0.0, 0.0);
+        s.smooth(1.0, 0.0);
+        assert_eq!(s.smooth(4.0, 0.0), 4.0);
+    }
+
+    #[test]
+    fn holds_still_within_the_hysteresis_band() {
+        let mut s = smoother(0.0, 0.2);
+        s.smooth(1.0, 0.0);
+        assert_eq!(s.smooth(1.1, 1.0), 1.0);
+        assert_eq!(s.smooth(1.3, 2.0), 1.3);
+        assert_eq!(s.smooth(1.2, 3.0), 1.3);
+    }
+
+    #[test]
+    fn follows_past_the_band_until_it_arrives() {
+        let mut s = smoother(1.0, 0.2);
+        s.smooth(1.0, 0.0);
+        assert_eq!(s.smooth(2.0, 0.5), 1.5);
+        // Now within the band, but still on its way.
+        assert_eq!(s.smooth(1.6, 1.0), 1.6);
+        assert_eq!(s.smooth(1.7, 2.0), 1.6);
+    }
+
+    #[test]
+    fn reset_jumps_and_stops_following() {
+        let mut s = smoother(1.0, 0.2);
+        s.smooth(1.0, 0.0);
+        s.smooth(3.0, 0.5);
+        assert_eq!(s.reset_to(1.0, 0.5), 1.0);
+        assert_eq!(s.smooth(1.1, 1.0), 1.0);
+    }
+
+    #[test]
+    fn skips_writes_below_min_write() {
+        let s = RateSmoother::default();
+        assert!(!s.worth_writing(1.005, 1.0));
+        assert!(s.worth_writing(1.01, 1.0));
+        assert!(s.worth_writing(0.5, 1.0));
+    }
+}
@@ -0,0 +1,325 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlVideoElement;
+
+use crate::rate_bounds;
+
+#[wasm_bindgen]
+extern "C" {
+    fn unixtime() -> f64;
+}
+
+/// Where the app gets the time from, so that a replay can substitute the recorded one.
+pub trait Clock {
+    /// Seconds since the epoch.
+    fn now(&self) -> f64;
+}
+
+/// The browser's clock.
+pub struct WallClock;
+
+impl Clock for WallClock {
+    fn now(&self) -> f64 {
+        unixtime()
+    }
+}
+
+/// A clock that shows whatever time it was last set to.
+#[derive(Debug, Default)]
+pub struct ReplayClock(Cell<f64>);
+
+impl ReplayClock {
+    pub fn set(&self, now: f64) {
+        self.0.set(now);
+    }
+}
+
+impl Clock for ReplayClock {
+    fn now(&self) -> f64 {
+        self.0.get()
+    }
+}
+
+/// What the app needs from the video, so that a replay can substitute a recorded one.
+pub trait VideoSource {
+    fn current_time(&self) -> f64;
+    fn set_current_time(&self, time: f64);
+    fn playback_rate(&self) -> f64;
+    /// Sets the playback rate, failing if the browser does not play at that rate.
+    fn set_rate(&self, rate: f64) -> Result<(), String>;
+    fn paused(&self) -> bool;
+    fn play(&self);
+    fn pause(&self);
+    /// The element on the page, for what only a real video can do, like drawing its frame.
+    fn element(&self) -> Option<&HtmlVideoElement> {
+        None
+    }
+}
+
+/// What a video is doing at one moment.
+///
+/// It is saved as a `[time, rate, paused]` array, since every logged event has one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(from = "(f64, f64, bool)", into = "(f64, f64, bool)")]
+pub struct VideoState {
+    pub time: f64,
+    pub rate: f64,
+    pub paused: bool,
+}
+
+impl VideoState {
+    pub fn of(video: &dyn VideoSource) -> Self {
+        Self {
+            time: video.current_time(),
+            rate: video.playback_rate(),
+            paused: video.paused(),
+        }
+    }
+}
+
+impl From<(f64, f64, bool)> for VideoState {
+    fn from((time, rate, paused): (f64, f64, bool)) -> Self {
+        Self { time, rate, paused }
+    }
+}
+
+impl From<VideoState> for (f64, f64, bool) {
+    fn from(state: VideoState) -> Self {
+        (state.time, state.rate, state.paused)
+    }
+}
+
+impl Default for VideoState {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            rate: 1.0,
+            paused: true,
+        }
+    }
+}
+
+/// A playback rate the video was given, and whether it took it.
+///
+/// It is saved as a `[rate, why]` array, where `why` is null unless the video refused the rate.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "(f64, Option<String>)", into = "(f64, Option<String>)")]
+pub struct RateWrite {
+    pub rate: f64,
+    pub result: Result<(), String>,
+}
+
+impl From<(f64, Option<String>)> for RateWrite {
+    fn from((rate, why): (f64, Option<String>)) -> Self {
+        Self {
+            rate,
+            result: why.map_or(Ok(()), Err),
+        }
+    }
+}
+
+impl From<RateWrite> for (f64, Option<String>) {
+    fn from(write: RateWrite) -> Self {
+        (write.rate, write.result.err())
+    }
+}
+
+/// The video element on the page, noting down every playback rate it is given.
+pub struct PageVideo {
+    element: HtmlVideoElement,
+    rates: Rc<RefCell<Vec<RateWrite>>>,
+}
+
+impl PageVideo {
+    pub fn new(element: HtmlVideoElement, rates: Rc<RefCell<Vec<RateWrite>>>) -> Self {
+        Self { element, rates }
+    }
+}
+
+impl VideoSource for PageVideo {
+    fn current_time(&self) -> f64 {
+        self.element.current_time()
+    }
+
+    fn set_current_time(&self, time: f64) {
+        self.element.set_current_time(time);
+    }
+
+    fn playback_rate(&self) -> f64 {
+        self.element.playback_rate()
+    }
+
+    fn set_rate(&self, rate: f64) -> Result<(), String> {
+        let result = rate_bounds::set_rate(&self.element, rate);
+        let write = RateWrite {
+            rate,
+            result: result.clone(),
+        };
+        self.rates.borrow_mut().push(write);
+        result
+    }
+
+    fn paused(&self) -> bool {
+        self.element.paused()
+    }
+
+    fn play(&self) {
+        let _ = self.element.play();
+    }
+
+    fn pause(&self) {
+        let _ = self.element.pause();
+    }
+
+    fn element(&self) -> Option<&HtmlVideoElement> {
+        Some(&self.element)
+    }
+}
+
+/// A stand-in for the video during a replay: it reports whatever state was recorded last, and
+/// notes down every playback rate it is given, like [`PageVideo`].
+pub struct ReplayVideo {
+    state: Cell<VideoState>,
+    present: Cell<bool>,
+    rates: Rc<RefCell<Vec<RateWrite>>>,
+    /// How the recorded video took the rates it was given, for the replay to answer the same.
+    results: RefCell<VecDeque<Result<(), String>>>,
+}
+
+impl ReplayVideo {
+    pub fn new(rates: Rc<RefCell<Vec<RateWrite>>>) -> Self {
+        Self {
+            state: Cell::new(VideoState::default()),
+            present: Cell::new(false),
+            rates,
+            results: RefCell::default(),
+        }
+    }
+
+    /// Answers the playback rates it is given from now on as the recorded video did, in order.
+    /// Rates beyond the recording are taken.
+    pub fn answer_as(&self, recorded: &[RateWrite]) {
+        *self.results.borrow_mut() = recorded.iter().map(|w| w.result.clone()).collect();
+    }
+
+    /// Puts the video in the recorded state, or takes it away if the page had no video yet.
+    pub fn show(&self, state: Option<VideoState>) {
+        self.present.set(state.is_some());
+        self.state.set(state.unwrap_or_default());
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.present.get()
+    }
+
+    fn change(&self, change: impl FnOnce(&mut VideoState)) {
+        let mut state = self.state.get();
+        change(&mut state);
+        self.state.set(state);
+    }
+}
+
+impl VideoSource for ReplayVideo {
+    fn current_time(&self) -> f64 {
+        self.state.get().time
+    }
+
+    fn set_current_time(&self, time: f64) {
+        self.change(|s| s.time = time);
+    }
+
+    fn playback_rate(&self) -> f64 {
+        self.state.get().rate
+    }
+
+    fn set_rate(&self, rate: f64) -> Result<(), String> {
+        let result = self.results.borrow_mut().pop_front().unwrap_or(Ok(()));
+        if result.is_ok() {
+            self.change(|s| s.rate = rate);
+        }
+        let write = RateWrite {
+            rate,
+            result: result.clone(),
+        };
+        self.rates.borrow_mut().push(write);
+        result
+    }
+
+    fn paused(&self) -> bool {
+        self.state.get().paused
+    }
+
+    fn play(&self) {
+        self.change(|s| s.paused = false);
+    }
+
+    fn pause(&self) {
+        self.change(|s| s.paused = true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_video_follows_what_it_is_told() {
+        let rates = Rc::default();
+        let video = ReplayVideo::new(Rc::clone(&rates));
+        assert!(!video.is_present());
+        video.show(Some(VideoState {
+            time: 3.0,
+            rate: 1.0,
+            paused: true,
+        }));
+        assert!(video.is_present());
+        video.play();
+        video.set_current_time(5.0);
+        assert_eq!(video.set_rate(1.25), Ok(()));
+        assert_eq!(
+            VideoState::of(&video),
+            VideoState {
+                time: 5.0,
+                rate: 1.25,
+                paused: false,
+            }
+        );
+        assert_eq!(rates.borrow()[0].rate, 1.25);
+        video.show(None);
+        assert!(!video.is_present());
+        assert_eq!(VideoState::of(&video), VideoState::default());
+    }
+
+    #[test]
+    fn replay_video_answers_as_the_recorded_one() {
+        let written = |rate, result| RateWrite { rate, result };
+        let refused = || Err("The browser does not play at 20".to_string());
+        let rates = Rc::default();
+        let video = ReplayVideo::new(Rc::clone(&rates));
+        video.answer_as(&[written(2.0, Ok(())), written(20.0, refused())]);
+        assert_eq!(video.set_rate(2.0), Ok(()));
+        assert_eq!(video.set_rate(20.0), refused());
+        assert_eq!(video.playback_rate(), 2.0);
+        assert_eq!(video.set_rate(3.0), Ok(()));
+        assert_eq!(
+            *rates.borrow(),
+            [
+                written(2.0, Ok(())),
+                written(20.0, refused()),
+                written(3.0, Ok(()))
+            ]
+        );
+    }
+
+    #[test]
+    fn replay_clock_shows_the_time_it_was_set_to() {
+        let clock = ReplayClock::default();
+        assert_eq!(clock.now(), 0.0);
+        clock.set(1234.5);
+        assert_eq!(clock.now(), 1234.5);
+    }
+}
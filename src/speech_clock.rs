@@ -0,0 +1,189 @@
+/// Measures how long the speaker has spent on the current cue.
+///
+/// Only time while the video is playing and not stalled on buffering counts. Time while the tab
+/// is hidden counts too, but since the browser throttles hidden tabs, a cue that was read partly
+/// in the background is not trusted as a timing sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeechClock {
+    /// Active time in finished stretches of the current cue.
+    accumulated: f64,
+    /// Wall-clock start of the stretch that is running now, if any.
+    running_since: Option<f64>,
+    paused: bool,
+    stalled: bool,
+    hidden: bool,
+    /// Whether the tab has been hidden at any point during the current cue.
+    was_hidden: bool,
+}
+
+/// The active time spent on a finished cue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeechSample {
+    pub seconds: f64,
+    /// Whether the sample can be fed to the rate estimate.
+    pub clean: bool,
+}
+
+/// Samples shorter than this are accidental double presses rather than reading.
+const MIN_SAMPLE_SECONDS: f64 = 0.2;
+
+impl Default for SpeechClock {
+    fn default() -> Self {
+        Self {
+            accumulated: 0.0,
+            running_since: None,
+            paused: true,
+            stalled: false,
+            hidden: false,
+            was_hidden: false,
+        }
+    }
+}
+
+impl SpeechClock {
+    pub fn set_paused(&mut self, paused: bool, now: f64) {
+        self.paused = paused;
+        self.update(now);
+    }
+
+    pub fn set_stalled(&mut self, stalled: bool, now: f64) {
+        self.stalled = stalled;
+        self.update(now);
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool, now: f64) {
+        self.hidden = hidden;
+        self.was_hidden |= hidden;
+        self.update(now);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
+
+    /// Active time spent on the current cue so far.
+    pub fn elapsed(&self, now: f64) -> f64 {
+        let running = self
+            .running_since
+            .map_or(0.0, |since| (now - since).max(0.0));
+        self.accumulated + running
+    }
+
+    /// Finishes the current cue, returning its sample, and starts timing the next one.
+    pub fn restart(&mut self, now: f64) -> SpeechSample {
+        let seconds = self.elapsed(now);
+        let sample = SpeechSample {
+            seconds,
+            clean: !self.was_hidden && seconds >= MIN_SAMPLE_SECONDS,
+        };
+        self.accumulated = 0.0;
+        self.was_hidden = self.hidden;
+        self.running_since = None;
+        self.update(now);
+        sample
+    }
+
+    /// Starts timing a cue again that `seconds` were already spent on, like after going back.
+    pub fn restart_from(&mut self, seconds: f64, now: f64) {
+        self.restart(now);
+        self.accumulated = seconds;
+    }
+
+    fn update(&mut self, now: f64) {
+        let active = !self.paused && !self.stalled;
+        match (active, self.running_since) {
+            (true, None) => self.running_since = Some(now),
+            (false, Some(since)) => {
+                self.accumulated += (now - since).max(0.0);
+                self.running_since = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing_from(now: f64) -> SpeechClock {
+        let mut clock = SpeechClock::default();
+        clock.set_paused(false, now);
+        clock
+    }
+
+    #[test]
+    fn does_not_run_until_played() {
+        let clock = SpeechClock::default();
+        assert!(!clock.is_running());
+        assert_eq!(clock.elapsed(100.0), 0.0);
+    }
+
+    #[test]
+    fn leaves_out_time_while_paused() {
+        let mut clock = playing_from(0.0);
+        clock.set_paused(true, 3.0);
+        assert!(!clock.is_running());
+        assert_eq!(clock.elapsed(10.0), 3.0);
+        clock.set_paused(false, 10.0);
+        assert_eq!(clock.elapsed(12.0), 5.0);
+        let sample = clock.restart(12.0);
+        assert_eq!(sample.seconds, 5.0);
+        assert!(sample.clean);
+    }
+
+    #[test]
+    fn leaves_out_a_stall_in_the_middle_of_a_cue() {
+        let mut clock = playing_from(0.0);
+        clock.set_stalled(true, 2.0);
+        clock.set_stalled(false, 6.0);
+        assert_eq!(clock.restart(9.0).seconds, 5.0);
+    }
+
+    #[test]
+    fn distrusts_a_cue_read_partly_in_a_hidden_tab() {
+        let mut clock = playing_from(0.0);
+        clock.set_hidden(true, 1.0);
+        clock.set_hidden(false, 2.0);
+        let sample = clock.restart(4.0);
+        assert_eq!(sample.seconds, 4.0);
+        assert!(!sample.clean);
+        // The next cue starts visible again, so it is trusted.
+        assert!(clock.restart(8.0).clean);
+    }
+
+    #[test]
+    fn distrusts_the_next_cue_when_still_hidden() {
+        let mut clock = playing_from(0.0);
+        clock.set_hidden(true, 1.0);
+        assert!(!clock.restart(4.0).clean);
+        assert!(!clock.restart(8.0).clean);
+    }
+
+    #[test]
+    fn skips_samples_shorter_than_the_minimum() {
+        let mut clock = playing_from(0.0);
+        assert!(!clock.restart(0.1).clean);
+        assert!(clock.restart(0.1 + MIN_SAMPLE_SECONDS).clean);
+    }
+
+    #[test]
+    fn keeps_running_into_the_next_cue() {
+        let mut clock = playing_from(0.0);
+        clock.restart(3.0);
+        assert!(clock.is_running());
+        assert_eq!(clock.elapsed(4.0), 1.0);
+    }
+
+    #[test]
+    fn restarts_from_time_already_spent() {
+        let mut clock = playing_from(0.0);
+        clock.set_hidden(true, 1.0);
+        clock.set_hidden(false, 2.0);
+        clock.restart_from(4.5, 3.0);
+        assert!(clock.is_running());
+        assert_eq!(clock.elapsed(5.0), 6.5);
+        // Going back starts a fresh sample, so the hidden stretch before it no longer counts.
+        assert!(clock.restart(5.0).clean);
+    }
+}